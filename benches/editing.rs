@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use first_rust_project::{Document, Position};
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 4] = [1_000, 10_000, 100_000, 1_000_000];
+const LINE: &str = "2021-11-04T12:00:00Z INFO request handled in 12ms by worker-7";
+
+fn open_document(lines: usize) -> Document {
+    let path = env::temp_dir().join(format!("editing-bench-{}.log", lines));
+    let contents = format!("{}\n", LINE).repeat(lines);
+    fs::write(&path, contents).expect("Could not write benchmark file");
+    let document = Document::open(path.to_str().expect("Benchmark path is not UTF-8"))
+        .expect("Could not open benchmark file");
+    fs::remove_file(&path).ok();
+    document
+}
+
+fn bench_edit<F: FnMut(&mut Document, &Position)>(criterion: &mut Criterion, name: &str, mut edit: F) {
+    let mut group = criterion.benchmark_group(name);
+    for lines in SIZES {
+        let mut document = open_document(lines);
+        let at = Position { x: LINE.len() / 2, y: lines / 2 };
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &at, |bencher, at| {
+            bencher.iter_custom(|iterations| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iterations {
+                    let start = Instant::now();
+                    edit(&mut document, black_box(at));
+                    elapsed += start.elapsed();
+                    while document.undo().is_some() {}
+                }
+                elapsed
+            });
+        });
+    }
+    group.finish();
+}
+
+fn lookup(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("row lookup");
+    for lines in SIZES {
+        let document = open_document(lines);
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &(lines / 2), |bencher, y| {
+            bencher.iter(|| black_box(document.row(black_box(*y))).is_some());
+        });
+    }
+    group.finish();
+}
+
+fn editing(criterion: &mut Criterion) {
+    bench_edit(criterion, "insert char", |document, at| document.insert(at, 'x'));
+    bench_edit(criterion, "insert line", |document, at| document.insert(at, '\n'));
+    bench_edit(criterion, "join lines", |document, at| {
+        document.insert(at, '\n');
+        document.backspace(&Position { x: 0, y: at.y.saturating_add(1) });
+    });
+}
+
+criterion_group!(benches, lookup, editing);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+use std::env;
+use std::io::{Error, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::Terminal;
+
+const CLIPBOARD_VAR: &str = "EDITOR_CLIPBOARD";
+
+const HELPERS: &[(&str, &[&str], &[&str])] = &[
+    ("wl-copy", &["wl-copy"], &["wl-paste", "--no-newline"]),
+    ("xclip", &["xclip", "-selection", "clipboard"], &["xclip", "-selection", "clipboard", "-o"]),
+    ("xsel", &["xsel", "--clipboard", "--input"], &["xsel", "--clipboard", "--output"]),
+    ("pbcopy", &["pbcopy"], &["pbpaste"]),
+];
+
+#[derive(PartialEq, Clone, Default)]
+pub enum Clipboard {
+    #[default]
+    Register,
+    Osc52,
+    Helper {
+        copy: Vec<String>,
+        paste: Option<Vec<String>>,
+    },
+}
+
+impl Clipboard {
+    pub fn detect() -> Result<Self, String> {
+        if let Ok(name) = env::var(CLIPBOARD_VAR) {
+            return Self::from_name(&name).ok_or(name);
+        }
+        let display = env::var_os("WAYLAND_DISPLAY").is_some() || env::var_os("DISPLAY").is_some();
+        if env::var_os("SSH_TTY").is_none() && (display || cfg!(target_os = "macos")) {
+            if let Some(helper) = HELPERS.iter().find_map(|(name, _, _)| Self::from_name(name)) {
+                return Ok(helper);
+            }
+        }
+        if env::var_os("TERM").is_some() {
+            return Ok(Clipboard::Osc52);
+        }
+        Ok(Clipboard::Register)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "register" | "none" => return Some(Clipboard::Register),
+            "osc52" => return Some(Clipboard::Osc52),
+            _ => (),
+        }
+        let to_args = |args: &[&str]| args.iter().map(|arg| (*arg).to_string()).collect::<Vec<String>>();
+        let (copy, paste) = match HELPERS.iter().find(|(helper, _, _)| *helper == name) {
+            Some((_, copy, paste)) => (to_args(copy), Some(to_args(paste)).filter(|paste| is_executable(&paste[0]))),
+            None => (name.split_whitespace().map(str::to_string).collect(), None),
+        };
+        if copy.first().is_some_and(|program| is_executable(program)) {
+            Some(Clipboard::Helper { copy, paste })
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Clipboard::Register => "register",
+            Clipboard::Osc52 => "osc52",
+            Clipboard::Helper { copy, .. } => &copy[0],
+        }
+    }
+
+    pub fn copy(&self, text: &str) -> Result<(), Error> {
+        match self {
+            Clipboard::Register => Ok(()),
+            Clipboard::Osc52 => Terminal::set_clipboard(text),
+            Clipboard::Helper { copy, .. } => {
+                let mut child = Command::new(&copy[0])
+                    .args(&copy[1..])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(text.as_bytes())?;
+                }
+                let status = child.wait()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(Error::other(format!("{} exited with {}", copy[0], status)))
+                }
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn paste(&self) -> Option<String> {
+        if let Clipboard::Helper { paste: Some(paste), .. } = self {
+            let output = Command::new(&paste[0])
+                .args(&paste[1..])
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            if output.status.success() {
+                return String::from_utf8(output.stdout).ok();
+            }
+        }
+        None
+    }
+}
+
+fn is_executable(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+    })
+}
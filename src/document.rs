@@ -1,121 +1,547 @@
+use crate::Encoding;
+use crate::highlighting::State;
 use crate::FileType;
+use crate::Indentation;
+use crate::History;
+use crate::Operation;
+use crate::Rope;
 use crate::Row;
 use crate::Position;
+use crate::Query;
 use crate::SearchDirection;
 use std::fs;
-use std::io::Write;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use unicode_segmentation::UnicodeSegmentation;
 
+#[derive(Default, PartialEq, Clone, Copy)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    fn detect(contents: &str) -> Self {
+        match contents.find('\n') {
+            Some(index) if contents[..index].ends_with('\r') => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct  Document {
-    rows: Vec<Row>,
+    rows: Rope,
     pub file_name: Option<String>,
     unsaved_changes: bool,
     file_type: FileType,
+    history: History,
+    line_ending: LineEnding,
+    saved_line_ending: LineEnding,
+    missing_final_newline: bool,
+    encoding: Encoding,
+    read_only: bool,
 }
 
 impl Document {
     pub fn open(filename: &str) -> Result<Self, std::io::Error> {
-        let contents = fs::read_to_string(filename)?;
-        let file_type = FileType::from(filename);
-        let mut rows = Vec::new();
+        let bytes = fs::read(filename)?;
+        let encoding = Encoding::detect(&bytes);
+        let contents = encoding.map_or_else(|| Encoding::decode_binary(&bytes), |encoding| encoding.decode(&bytes));
+        let mut rows = Rope::default();
         for value in contents.lines() {
-            let mut row = Row::from(value);
-            row.highlight(file_type.highlighting_options(), None);
-            rows.push(row);
+            rows.push(Row::from(value));
         }
-        Ok(Self { 
+        let mut document = Self {
             rows,
             file_name: Some(filename.to_string()),
             unsaved_changes: false,
-            file_type,
-         })
+            file_type: FileType::default(),
+            history: History::default(),
+            line_ending: LineEnding::detect(&contents),
+            saved_line_ending: LineEnding::detect(&contents),
+            missing_final_newline: !contents.is_empty() && !contents.ends_with('\n'),
+            encoding: encoding.unwrap_or_default(),
+            read_only: encoding.is_none(),
+        };
+        document.file_type = document.detect_file_type(filename);
+        document.highlight(None);
+        Ok(document)
+    }
+
+    #[must_use]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    #[must_use]
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    #[must_use]
+    pub fn has_final_newline(&self) -> bool {
+        !self.missing_final_newline
+    }
+
+    pub fn toggle_line_ending(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+        self.line_ending = match self.line_ending {
+            LineEnding::Lf => LineEnding::Crlf,
+            LineEnding::Crlf => LineEnding::Lf,
+        };
+        self.unsaved_changes = !self.is_saved();
+        true
     }
     
+    #[must_use]
     pub fn file_type(&self) -> String {
         self.file_type.name()
     }
 
+    #[must_use]
+    pub fn indentation(&self) -> Indentation {
+        self.file_type.indentation()
+    }
+
+    fn detect_file_type(&self, file_name: &str) -> FileType {
+        let len = self.rows.len();
+        let head = len.min(FileType::SCANNED_LINES);
+        let tail = len.saturating_sub(FileType::SCANNED_LINES).max(head);
+        let lines: Vec<&str> = (0..head)
+            .chain(tail..len)
+            .filter_map(|y| self.rows.get(y))
+            .map(Row::as_str)
+            .collect();
+        FileType::detect(file_name, &lines)
+    }
+
+    #[must_use]
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
     }
 
+    #[must_use]
     pub fn is_empty(&self) -> bool {
         self.rows.is_empty()
     }
 
+    #[must_use]
     pub fn len(&self) -> usize {
         self.rows.len()
     }
 
-    pub fn insert_newline(&mut self,at: &Position) {
-        if at.y == self.len() {
-            self.rows.push(Row::default());
+    pub fn insert(&mut self,at: &Position,c: char) {
+        if at.y > self.len() || self.read_only {
             return;
         }
-        let curr_row =  &mut self.rows[at.y];
-        let mut new_row = curr_row.split(at.x);
-        curr_row.highlight(self.file_type.highlighting_options(), None);
-        new_row.highlight(self.file_type.highlighting_options(), None);
-        self.rows.insert(at.y + 1, new_row)
-        
+        let mut operations = Vec::new();
+        let after = if c == '\n' {
+            Position { x: 0, y: at.y.saturating_add(1) }
+        } else {
+            if at.y == self.len() {
+                operations.push(Operation::Insert { at: Position { x: 0, y: at.y }, text: String::from("\n") });
+            }
+            Position { x: at.x.saturating_add(1), y: at.y }
+        };
+        operations.push(Operation::Insert { at: at.clone(), text: c.to_string() });
+        for operation in &operations {
+            self.apply(operation);
+        }
+        self.history.record(operations, at, &after);
+        self.unsaved_changes = true;
     }
 
-    pub fn insert(&mut self,at: &Position,c: char) {
-        if at.y > self.len() {
-            return;
+    pub fn insert_newline(&mut self, at: &Position, smart: bool) -> Position {
+        let Some(row) = self.rows.get(at.y) else {
+            if at.y != self.len() || self.read_only {
+                return at.clone();
+            }
+            self.insert(at, '\n');
+            return Position { x: 0, y: at.y.saturating_add(1) };
+        };
+        let before = row.substring(0, at.x);
+        let after = row.substring(at.x, row.len());
+        let indent: String = before.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        let mut text = format!("\n{indent}");
+        let mut cursor = None;
+        let indentation = self.file_type.indentation();
+        if let Some(opener) = before.trim_end().chars().last().filter(|c| smart && indentation.opens(*c)) {
+            text.push_str(&indentation.unit());
+            let inner = text.len().saturating_sub(1);
+            if after.trim_start().chars().next().is_some_and(|c| indentation.closes(c) && c != opener) {
+                text.push('\n');
+                text.push_str(&indent);
+                cursor = Some(Position { x: inner, y: at.y.saturating_add(1) });
+            }
         }
+        let end = self.insert_text(at, &text);
+        cursor.unwrap_or(end)
+    }
+
+    pub fn dedent(&mut self, at: &Position) -> Position {
+        let Some(row) = self.rows.get(at.y) else {
+            return at.clone();
+        };
+        let before = row.substring(0, at.x);
+        if before.is_empty() || !before.chars().all(|c| c == ' ' || c == '\t') {
+            return at.clone();
+        }
+        let width = if before.ends_with('\t') {
+            1
+        } else {
+            let spaces = before.len().saturating_sub(before.trim_end_matches(' ').len());
+            spaces.min(self.file_type.indentation().width())
+        };
+        let start = Position { x: at.x.saturating_sub(width), y: at.y };
+        self.delete_range(&start, at);
+        start
+    }
+
+    #[must_use]
+    pub fn text_range(&self, start: &Position, end: &Position) -> String {
+        let (start, end) = (self.clamp(start), self.clamp(end));
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            if let Some(row) = self.rows.get(y) {
+                let from = if y == start.y { start.x } else { 0 };
+                let to = if y == end.y { end.x } else { row.len() };
+                lines.push(row.substring(from, to));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn insert_text(&mut self, at: &Position, text: &str) -> Position {
+        if at.y > self.len() || self.read_only || text.is_empty() {
+            return at.clone();
+        }
+        let mut operations = Vec::new();
+        if at.y == self.len() {
+            operations.push(Operation::Insert { at: Position { x: 0, y: at.y }, text: String::from("\n") });
+        }
+        operations.push(Operation::Insert { at: at.clone(), text: text.to_string() });
+        for operation in &operations {
+            self.apply(operation);
+        }
+        let last_line = text.rsplit('\n').next().unwrap_or_default().graphemes(true).count();
+        let newlines = text.matches('\n').count();
+        let after = if newlines == 0 {
+            Position { x: at.x.saturating_add(last_line), y: at.y }
+        } else {
+            Position { x: last_line, y: at.y.saturating_add(newlines) }
+        };
+        self.history.record_step(operations, at, &after);
         self.unsaved_changes = true;
-        if c == '\n' {
-            self.insert_newline(at);
+        after
+    }
+
+    pub fn delete_range(&mut self, start: &Position, end: &Position) {
+        if self.read_only {
             return;
         }
-        if at.y == self.len() {
-            let mut row = Row::default();
-            row.insert(0,c);
-            row.highlight(self.file_type.highlighting_options(), None);
-            self.rows.push(row);
-        } else if at.y < self.len() {
-            let row = &mut self.rows[at.y];
-            row.insert(at.x,c);
-            row.highlight(self.file_type.highlighting_options(), None);
+        let text = self.text_range(start, end);
+        if text.is_empty() {
+            return;
+        }
+        let start = self.clamp(start);
+        let operation = Operation::Delete { at: start.clone(), text };
+        self.apply(&operation);
+        self.history.record_step(vec![operation], &start, &start);
+        self.unsaved_changes = true;
+    }
+
+    fn clamp(&self, at: &Position) -> Position {
+        match self.len().checked_sub(1) {
+            Some(last) if at.y > last => Position { x: self.rows[last].len(), y: last },
+            Some(_) => Position { x: at.x.min(self.rows[at.y].len()), y: at.y },
+            None => Position::default(),
         }
     }
 
     pub fn delete(&mut self, at: &Position){
-        let len = self.len();
-        if at.y >= len {
+        self.remove(at, at);
+    }
+
+    pub fn backspace(&mut self, at: &Position) {
+        let position = if at.x > 0 {
+            Position { x: at.x.saturating_sub(1), y: at.y }
+        } else if at.y > 0 {
+            let y = at.y.saturating_sub(1);
+            Position { x: self.rows.get(y).map_or(0, Row::len), y }
+        } else {
+            return;
+        };
+        self.remove(&position, at);
+    }
+
+    pub fn replace(&mut self, at: &Position, query: &Query, replacement: &str) -> Option<Position> {
+        let (operations, after) = self.substitute(at, query, replacement)?;
+        self.history.record_step(operations, at, &after);
+        self.unsaved_changes = true;
+        Some(after)
+    }
+
+    pub fn replace_all(&mut self, query: &Query, replacement: &str, from: &Position, until: Option<&Position>) -> usize {
+        let mut operations = Vec::new();
+        let mut count = 0;
+        let mut position = from.clone();
+        let mut until = until.cloned();
+        while let Some(at) = self.find(query, &position, SearchDirection::Forward) {
+            if until.as_ref().is_some_and(|until| (at.y, at.x) >= (until.y, until.x)) {
+                break;
+            }
+            let len = self.rows.get(at.y).map_or(0, Row::len);
+            if let Some((mut substitution, after)) = self.substitute(&at, query, replacement) {
+                operations.append(&mut substitution);
+                position = after;
+                count += 1;
+                if let Some(until) = until.as_mut().filter(|until| until.y == at.y) {
+                    until.x = until.x.saturating_add(self.rows.get(at.y).map_or(0, Row::len)).saturating_sub(len);
+                }
+            } else {
+                position = Position { x: at.x.saturating_add(1), y: at.y };
+            }
+        }
+        if count > 0 {
+            self.history.record_step(operations, from, &position);
+            self.unsaved_changes = true;
+        }
+        count
+    }
+
+    fn substitute(&mut self, at: &Position, query: &Query, replacement: &str) -> Option<(Vec<Operation>, Position)> {
+        if self.read_only {
+            return None;
+        }
+        let (matched, expanded) = self.rows.get(at.y)?.replacement(query, at.x, replacement)?;
+        let mut after = Position { x: at.x.saturating_add(expanded[..].graphemes(true).count()), y: at.y };
+        if matched.is_empty() {
+            after.x = after.x.saturating_add(1);
+        }
+        let operations = vec![
+            Operation::Delete { at: at.clone(), text: matched },
+            Operation::Insert { at: at.clone(), text: expanded },
+        ];
+        for operation in &operations {
+            self.apply(operation);
+        }
+        Some((operations, after))
+    }
+
+    #[must_use]
+    pub fn edit_checkpoint(&self) -> usize {
+        self.history.checkpoint()
+    }
+
+    pub fn group_edits(&mut self, checkpoint: usize) {
+        self.history.join(checkpoint);
+    }
+
+    pub fn undo(&mut self) -> Option<Position> {
+        let step = self.history.undo()?;
+        for operation in step.operations.iter().rev() {
+            self.apply(&operation.inverse());
+        }
+        self.unsaved_changes = !self.is_saved();
+        Some(step.before)
+    }
+
+    pub fn redo(&mut self) -> Option<Position> {
+        let step = self.history.redo()?;
+        for operation in &step.operations {
+            self.apply(operation);
+        }
+        self.unsaved_changes = !self.is_saved();
+        Some(step.after)
+    }
+
+    fn remove(&mut self, at: &Position, before: &Position) {
+        if self.read_only {
             return;
         }
-        if at.x == self.rows[at.y].len() && at.y < len - 1 {
-            let next_row = self.rows.remove(at.y+1);
-            let row = &mut self.rows[at.y];
-            row.append(&next_row);
-            row.highlight(self.file_type.highlighting_options(), None);
-        }else {
-            let row = &mut self.rows[at.y];
-            row.delete(at.x);
-            row.highlight(self.file_type.highlighting_options(), None);
+        let text = match self.rows.get(at.y) {
+            Some(row) if at.x == row.len() && at.y.saturating_add(1) < self.len() => String::from("\n"),
+            Some(row) => match row.grapheme(at.x) {
+                Some(grapheme) => grapheme.to_string(),
+                None => return,
+            },
+            None => return,
+        };
+        let operation = Operation::Delete { at: at.clone(), text };
+        self.apply(&operation);
+        self.history.record(vec![operation], before, at);
+        self.unsaved_changes = true;
+    }
+
+    fn apply(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Insert { at, text } if text == "\n" && at.y == self.len() => {
+                self.rows.push(Row::default());
+            }
+            Operation::Insert { at, text } => {
+                let mut lines = text.split('\n');
+                let first = lines.next().unwrap_or_default();
+                let rest: Vec<&str> = lines.collect();
+                let row = &mut self.rows[at.y];
+                let tail = if rest.is_empty() {
+                    None
+                } else {
+                    Some(row.split(at.x))
+                };
+                row.insert_str(at.x, first);
+                if let Some(tail) = tail {
+                    let last = rest.len() - 1;
+                    for (index, line) in rest.iter().enumerate() {
+                        let mut new_row = Row::from(*line);
+                        if index == last {
+                            new_row.append(&tail);
+                        }
+                        self.rows.insert(at.y + 1 + index, new_row);
+                    }
+                }
+            }
+            Operation::Delete { at, text } if text == "\n" && at.y.saturating_add(1) == self.len() => {
+                self.rows.remove(at.y);
+            }
+            Operation::Delete { at, text } => {
+                let lines: Vec<&str> = text.split('\n').collect();
+                let newlines = lines.len() - 1;
+                if newlines == 0 {
+                    let row = &mut self.rows[at.y];
+                    for _ in text[..].graphemes(true) {
+                        row.delete(at.x);
+                    }
+                } else {
+                    let mut last_row = Row::default();
+                    for _ in 0..newlines {
+                        last_row = self.rows.remove(at.y + 1);
+                    }
+                    let tail = last_row.split(lines[newlines].graphemes(true).count());
+                    let row = &mut self.rows[at.y];
+                    let _ = row.split(at.x);
+                    row.append(&tail);
+                }
+            }
         }
+        let (at, inserted) = match operation {
+            Operation::Insert { at, text } => (at, text.matches('\n').count()),
+            Operation::Delete { at, .. } => (at, 0),
+        };
+        self.rehighlight(at.y, at.y.saturating_add(inserted));
+    }
 
+    fn rehighlight(&mut self, from: usize, to: usize) {
+        let options = self.file_type.highlighting_options();
+        let mut state = self.state_before(from);
+        let mut y = from;
+        while let Some(row) = self.rows.get_mut(y) {
+            let previous = row.highlight_state();
+            row.highlight(options, None, state);
+            state = row.highlight_state();
+            if y >= to && state == previous {
+                break;
+            }
+            y += 1;
+        }
     }
 
-    pub fn save(&mut self) -> std::io::Result<()> {
+    fn state_before(&self, y: usize) -> State {
+        y.checked_sub(1)
+            .and_then(|previous| self.rows.get(previous))
+            .map_or(State::Normal, Row::highlight_state)
+    }
+
+    pub fn save(&mut self) -> std::io::Result<Option<std::io::Error>> {
+        if self.read_only {
+            return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "binary file is opened read-only"));
+        }
         if let Some(file_name) = &self.file_name {
-            let mut file = fs::File::create(file_name)?;
-            self.file_type = FileType::from(file_name);
-            for row in &mut self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n")?;
-                row.highlight(self.file_type.highlighting_options(), None)
+            let target = fs::canonicalize(file_name).unwrap_or_else(|_| PathBuf::from(file_name));
+            let temp_path = Self::temp_path(&target);
+            if let Err(error) = self.write_atomically(&target, &temp_path) {
+                fs::remove_file(&temp_path).ok();
+                return Err(error);
             }
+            self.file_type = self.detect_file_type(file_name);
+            self.highlight(None);
+            self.mark_saved();
+            return Ok(Self::sync_directory(&target).err());
         }
         
+        self.mark_saved();
+        Ok(None)
+    }
+
+    fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.saved_line_ending = self.line_ending;
         self.unsaved_changes = false;
+    }
+
+    fn is_saved(&self) -> bool {
+        self.history.is_saved() && self.line_ending == self.saved_line_ending
+    }
+
+    fn write_atomically(&self, target: &Path, temp_path: &Path) -> std::io::Result<()> {
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(temp_path)?;
+        let mut writer = BufWriter::new(file);
+        let line_ending = self.encoding.encode(self.line_ending.as_str())?;
+        writer.write_all(self.encoding.bom())?;
+        for (index, row) in self.rows.iter().enumerate() {
+            writer.write_all(&self.encoding.encode(row.as_str())?)?;
+            if index.saturating_add(1) < self.len() || !self.missing_final_newline {
+                writer.write_all(&line_ending)?;
+            }
+        }
+        let file = writer.into_inner().map_err(std::io::IntoInnerError::into_error)?;
+        if let Ok(metadata) = fs::metadata(target) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(temp_path, target)
+    }
+
+    fn sync_directory(target: &Path) -> std::io::Result<()> {
+        #[cfg(unix)]
+        if let Some(directory) = target.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::File::open(directory)?.sync_all()?;
+        }
         Ok(())
     }
 
-    pub fn find(&self, query: &str, at: &Position, direction: SearchDirection) -> Option<Position> {
+    fn temp_path(target: &Path) -> PathBuf {
+        let name = target.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        target.with_file_name(format!(".{}.{}.tmp", name, process::id()))
+    }
+
+    #[must_use]
+    pub fn find(&self, query: &Query, at: &Position, direction: SearchDirection) -> Option<Position> {
         if at.y >= self.rows.len(){
             return None;
         }
@@ -133,7 +559,7 @@ impl Document {
         };
         for _ in start..end {
             if let Some(row) = self.rows.get(position.y) {
-                if let Some(x) = row.find(&query, position.x, direction) {
+                if let Some(x) = row.find(query, position.x, direction) {
                     position.x = x;
                     return Some(position);
                 }
@@ -151,13 +577,167 @@ impl Document {
         None
     }
 
+    #[must_use]
+    pub fn find_wrapping(&self, query: &Query, at: &Position, direction: SearchDirection) -> Option<(Position, bool)> {
+        if let Some(position) = self.find(query, at, direction) {
+            return Some((position, false));
+        }
+        let restart = if direction == SearchDirection::Forward {
+            Position::default()
+        } else {
+            let y = self.len().checked_sub(1)?;
+            Position { x: self.rows[y].len(), y }
+        };
+        self.find(query, &restart, direction).map(|position| (position, true))
+    }
+
+    #[must_use]
+    pub fn match_index(&self, at: &Position) -> (Option<usize>, usize) {
+        let mut current = None;
+        let mut total = 0;
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in row.matches() {
+                total += 1;
+                if y == at.y && *x == at.x {
+                    current = Some(total);
+                }
+            }
+        }
+        (current, total)
+    }
+
+    #[must_use]
     pub fn needs_saving(&self) -> bool{
         self.unsaved_changes
     }
 
-    pub fn highlight(&mut self, word: Option<&str>) {
-        for row in &mut self.rows {
-            row.highlight(self.file_type.highlighting_options(), word)
+    pub fn highlight_match(&mut self, at: &Position, query: &Query) {
+        let options = self.file_type.highlighting_options();
+        let state = self.state_before(at.y);
+        if let Some(row) = self.rows.get_mut(at.y) {
+            row.highlight(options, None, state);
+            row.highlight_match(query, at.x);
+        }
+    }
+
+    pub fn highlight(&mut self, word: Option<&Query>) {
+        let options = self.file_type.highlighting_options();
+        let mut state = State::Normal;
+        for row in self.rows.iter_mut() {
+            row.highlight(options, word, state);
+            state = row.highlight_state();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchOptions;
+
+    fn contents(document: &Document) -> Vec<String> {
+        (0..document.len()).filter_map(|y| document.row(y)).map(|row| row.as_str().to_string()).collect()
+    }
+
+    #[test]
+    fn newline_on_the_last_line_adds_one_row() {
+        let mut document = Document::default();
+        let after = document.insert_newline(&Position { x: 0, y: 0 }, true);
+        assert_eq!(contents(&document), vec![String::new()]);
+        assert_eq!((after.x, after.y), (0, 1));
+        document.undo();
+        assert!(document.is_empty());
+    }
+
+    #[test]
+    fn undo_keeps_a_changed_line_ending_modified() {
+        let mut document = Document::default();
+        assert!(document.toggle_line_ending());
+        assert!(document.needs_saving());
+        document.insert(&Position { x: 0, y: 0 }, 'a');
+        document.undo();
+        assert!(document.needs_saving());
+        assert!(document.toggle_line_ending());
+        assert!(!document.needs_saving());
+    }
+
+    #[test]
+    fn read_only_documents_keep_their_line_ending() {
+        let mut document = Document { read_only: true, ..Document::default() };
+        assert!(!document.toggle_line_ending());
+        assert!(document.line_ending() == LineEnding::Lf);
+        assert!(!document.needs_saving());
+    }
+
+    #[test]
+    fn replace_all_stops_before_the_end_position() {
+        let mut document = Document::default();
+        document.insert_text(&Position { x: 0, y: 0 }, "ab ab\nab");
+        let query = Query::new("ab", SearchOptions::default()).unwrap();
+        let until = Position { x: 3, y: 0 };
+        assert_eq!(document.replace_all(&query, "xyz", &Position::default(), Some(&until)), 1);
+        assert_eq!(contents(&document), vec!["xyz ab", "ab"]);
+        assert_eq!(document.replace_all(&query, "", &Position { x: 4, y: 0 }, None), 2);
+        assert_eq!(contents(&document), vec!["xyz ", ""]);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("document-{}-{}", name, process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_replaces_the_file_atomically() {
+        let dir = temp_dir("atomic");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "old\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let mut document = Document::open(path.to_str().unwrap()).unwrap();
+        document.insert(&Position { x: 0, y: 0 }, 'n');
+        assert!(document.save().unwrap().is_none());
+        assert!(!document.needs_saving());
+        assert_eq!(fs::read(&path).unwrap(), b"nold\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
         }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).ok();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn save_keeps_crlf_and_a_missing_final_newline() {
+        let dir = temp_dir("line-endings");
+        let path = dir.join("windows.txt");
+        fs::write(&path, "a\r\nb").unwrap();
+        let mut document = Document::open(path.to_str().unwrap()).unwrap();
+        assert!(document.line_ending() == LineEnding::Crlf);
+        document.insert(&Position { x: 1, y: 1 }, 'c');
+        document.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\nbc");
+        document.toggle_line_ending();
+        document.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a\nbc");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn dedent_undoes_with_the_inserted_character() {
+        let mut document = Document::default();
+        document.insert_text(&Position { x: 0, y: 0 }, "{\n    ");
+        let checkpoint = document.edit_checkpoint();
+        let at = document.dedent(&Position { x: 4, y: 1 });
+        document.insert(&at, '}');
+        document.group_edits(checkpoint);
+        assert_eq!(contents(&document), vec!["{", "}"]);
+        document.undo();
+        assert_eq!(contents(&document), vec!["{", "    "]);
+    }
+}
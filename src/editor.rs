@@ -1,16 +1,32 @@
 use crate::Terminal;
 use crate::Document;
 use crate::Row;
+use crate::row::truncate_to_width;
+use crate::Wrap;
+use crate::Query;
+use crate::SearchOptions;
+use crate::Layout;
+use crate::Rect;
+use crate::Split;
+use crate::Window;
+use crate::Offset;
+use crate::Clipboard;
+use crate::SyntaxDefinition;
+use crate::Theme;
 use std::time::{Duration, Instant};
 use std::env;
+use std::fs;
+use std::mem;
+use std::path::{self, PathBuf};
+use unicode_width::UnicodeWidthStr;
 use crossterm::{
     event::{KeyCode, KeyEvent, Event,self, KeyModifiers},
-    style::{Colors, Color},
 };
 
-const STATUS_BG_COLOR: Color = Color::Rgb{r: 239, g: 239, b: 239};
-const STATUS_FG_COLOR: Color = Color::Rgb { r: 63, g: 63, b: 63 };
+const RESIZE_STEP: i16 = 5;
 const QUIT_TIMES: u8 = 3;
+const TAB_WIDTH: usize = 4;
+const SIGN_COLUMNS: usize = 1;
 
 #[derive(PartialEq,Clone, Copy)]
 pub enum SearchDirection {
@@ -33,58 +49,167 @@ impl StatusMessage{
     }
 }
 
+#[derive(PartialEq, Clone, Copy)]
+enum AutoIndent {
+    Off,
+    Copy,
+    Smart,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum LineNumbers {
+    Off,
+    Absolute,
+    Relative,
+}
+
+#[derive(Default)]
+struct Buffer {
+    document: Document,
+    cursor_position: Position,
+    offset: Offset,
+}
+impl Buffer {
+    fn from(document: Document) -> Self {
+        Self { document, cursor_position: Position::default(), offset: Offset::default() }
+    }
+}
+
 pub struct Editor {
     terminal: Terminal,
     should_quit: bool,
     cursor_position: Position,
-    offset: Position,
+    offset: Offset,
     document: Document,
     status_message: StatusMessage,
     quit_times: u8,
-    
+    search_options: SearchOptions,
+    prompt_status: String,
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
+    windows: Vec<Window>,
+    layout: Layout,
+    active_window: usize,
+    selection_anchor: Option<Position>,
+    register: String,
+    clipboard: Clipboard,
+    register_is_newer: bool,
+    theme: Theme,
+    themes: Vec<Theme>,
+    tab_width: usize,
+    wrap: Wrap,
+    line_numbers: LineNumbers,
+    auto_indent: AutoIndent,
 }
 
 impl Editor {
     
-    fn explode(e: std::io::Error){
+    fn explode(e: &std::io::Error){
         Terminal::clear_screen();
         panic!("{}",e);
     }
     
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         let args: Vec<String> = env::args().collect();
-        let mut initial_status = String::from("HELP: Ctrl-c or Esc = quit | Ctrl-s = save | Ctrl-f = find");
-        let document = if args.len() > 1 {
-            let file_name = &args[1];
-            let doc = Document::open(&file_name);
-            if let Ok(doc) = doc {
-                doc
-            } else {
-                initial_status = format!("Error could not open file {}",file_name);
-                Document::default()
+        let mut warnings = Vec::new();
+        let help = String::from("HELP: Ctrl-q or Esc = quit | Shift-arrows = select | Ctrl-c/Ctrl-x/Ctrl-v = copy/cut/paste | Ctrl-s = save | Ctrl-f = find | Ctrl-r = replace | Ctrl-z/Ctrl-y = undo/redo | Ctrl-o = open | Ctrl-n/Ctrl-p = next/previous buffer | Ctrl-b = buffers | Ctrl-w = close | Alt-s/Alt-v = split | Alt-arrows = focus | Alt-=/Alt-- = resize | Alt-q = close window | Ctrl-e = LF/CRLF | Ctrl-t = theme | Alt-w = soft wrap | Alt-n = line numbers | Alt-i = auto indent");
+        let syntax_errors = SyntaxDefinition::load_all();
+        warnings.extend(summarize("Syntax definition error", &syntax_errors));
+        let mut themes = Theme::built_in();
+        let (custom_themes, theme_errors) = Theme::load_all(&themes);
+        themes.extend(custom_themes);
+        warnings.extend(summarize("Theme error", &theme_errors));
+        let theme_name = env::var("EDITOR_THEME").unwrap_or_else(|_| "dark".to_string());
+        let theme = themes.iter().find(|theme| theme.name() == theme_name).cloned().unwrap_or_else(|| {
+            warnings.push(format!("Unknown theme {theme_name}, using dark"));
+            Theme::default()
+        });
+        let clipboard = Clipboard::detect().unwrap_or_else(|name| {
+            warnings.push(format!("Clipboard {name} is not available, using the internal register"));
+            Clipboard::Register
+        });
+        let mut buffers = Vec::new();
+        let mut opened = Vec::new();
+        for file_name in args.iter().skip(1) {
+            let path = canonical_path(file_name);
+            if opened.contains(&path) {
+                continue;
+            }
+            opened.push(path);
+            match Self::open_document(file_name) {
+                Ok((document, warning)) => {
+                    warnings.extend(warning);
+                    buffers.push(Buffer::from(document));
+                }
+                Err(error) => warnings.push(error),
             }
-        }else{
-            Document::default()
-        };
+        }
+        let initial_status = if warnings.is_empty() { help } else { warnings.join(" | ") };
+        if buffers.is_empty() {
+            buffers.push(Buffer::default());
+        }
+        let document = mem::take(&mut buffers[0].document);
+        let terminal = Terminal::default().expect("Failed to initialize terminal");
+        let theme = theme.adapt(terminal.color_support());
         Self {
             
-            terminal: Terminal::default().expect("Failed to initialize terminal"),
+            terminal,
             should_quit: false,
             cursor_position: Position::default(),
             document,
-            offset: Position::default(),
+            offset: Offset::default(),
             status_message: StatusMessage::from(initial_status),
             quit_times: QUIT_TIMES,
+            search_options: SearchOptions::default(),
+            prompt_status: String::new(),
+            buffers,
+            current_buffer: 0,
+            windows: vec![Window::default()],
+            layout: Layout::Window(0),
+            active_window: 0,
+            selection_anchor: None,
+            register: String::new(),
+            clipboard,
+            register_is_newer: false,
+            theme,
+            themes,
+            tab_width: env::var("EDITOR_TAB_WIDTH")
+                .ok()
+                .and_then(|width| width.parse().ok())
+                .filter(|width| *width > 0)
+                .unwrap_or(TAB_WIDTH),
+            wrap: Wrap::Off,
+            line_numbers: LineNumbers::Off,
+            auto_indent: AutoIndent::Smart,
         }
     }
     
+    fn open_document(file_name: &str) -> Result<(Document, Option<String>), String> {
+        match Document::open(file_name) {
+            Ok(document) => {
+                let warning = if document.is_read_only() {
+                    Some(format!("WARNING! {file_name} looks like a binary file and was opened read-only"))
+                } else {
+                    None
+                };
+                Ok((document, warning))
+            }
+            Err(error) => Err(format!("Error could not open file {file_name}: {error}")),
+        }
+    }
+    
+    #[allow(clippy::too_many_lines)]
     fn process_keypress(&mut self) -> crossterm::Result<()> {
-        let pressed_key = self.read_key()?;
+        let pressed_key = Self::read_key()?;
         match (pressed_key.code, pressed_key.modifiers){
-            (KeyCode::Char('c'),KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
-                if self.quit_times > 0 && self.document.needs_saving() {
+            (KeyCode::Esc, _) if self.selection_anchor.is_some() => self.selection_anchor = None,
+            (KeyCode::Char('q'),KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
+                let unsaved = self.unsaved_buffers();
+                if self.quit_times > 0 && unsaved > 0 {
                     self.status_message = StatusMessage::from(
-                        format!("WARNING! File has unsaved changes still. Press Esc {} more times to quit", self.quit_times));
+                        format!("WARNING! {} buffer(s) have unsaved changes still. Press Esc {} more times to quit", unsaved, self.quit_times));
                         self.quit_times -= 1;
                         return Ok(());
                     }
@@ -92,22 +217,90 @@ impl Editor {
                 }
                 (KeyCode::Char('s'),KeyModifiers::CONTROL) => self.save(),
                 (KeyCode::Char('f'),KeyModifiers::CONTROL) => self.search(),
-                (KeyCode::Down, _) | (KeyCode::Up, _) | (KeyCode::Left, _) | (KeyCode::Right, _) | (KeyCode::PageDown,_) |
-                (KeyCode::PageUp,_)   => self.move_cursor(pressed_key.code),
-                (KeyCode::Delete,_) => self.document.delete(&self.cursor_position),
-                (KeyCode::Backspace,_) => {
-                    if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                        self.move_cursor(KeyCode::Left);
-                        self.document.delete(&self.cursor_position);
+                (KeyCode::Char('r'),KeyModifiers::CONTROL) => self.replace()?,
+                (KeyCode::Char('z'),KeyModifiers::CONTROL) => self.undo(),
+                (KeyCode::Char('y'),KeyModifiers::CONTROL) => self.redo(),
+                (KeyCode::Char('o'),KeyModifiers::CONTROL) => self.open(),
+                (KeyCode::Char('n'),KeyModifiers::CONTROL) => {
+                    self.switch_buffer(self.current_buffer.saturating_add(1) % self.buffers.len());
+                }
+                (KeyCode::Char('p'),KeyModifiers::CONTROL) => {
+                    self.switch_buffer(self.current_buffer.checked_sub(1).unwrap_or(self.buffers.len() - 1));
+                }
+                (KeyCode::Char('b'),KeyModifiers::CONTROL) => self.list_buffers(),
+                (KeyCode::Char('t'),KeyModifiers::CONTROL) => self.choose_theme(),
+                (KeyCode::Char('w'),KeyModifiers::CONTROL) => self.close_buffer()?,
+                (KeyCode::Char('e'),KeyModifiers::CONTROL) => {
+                    let message = if self.document.toggle_line_ending() {
+                        format!("Line endings set to {}", self.document.line_ending().name())
+                    } else {
+                        String::from("File is read-only.")
+                    };
+                    self.status_message = StatusMessage::from(message);
+                }
+                (KeyCode::Char('s'),KeyModifiers::ALT) => self.split_window(Split::Horizontal),
+                (KeyCode::Char('v'),KeyModifiers::ALT) => self.split_window(Split::Vertical),
+                (KeyCode::Char('q'),KeyModifiers::ALT) => self.close_window(),
+                (KeyCode::Char('='),KeyModifiers::ALT) => self.resize_window(RESIZE_STEP),
+                (KeyCode::Char('-'),KeyModifiers::ALT) => self.resize_window(RESIZE_STEP.saturating_neg()),
+                (KeyCode::Char('w'),KeyModifiers::ALT) => self.toggle_wrap(),
+                (KeyCode::Char('n'),KeyModifiers::ALT) => self.toggle_line_numbers(),
+                (KeyCode::Char('i'),KeyModifiers::ALT) => self.toggle_auto_indent(),
+                (KeyCode::Down | KeyCode::Up | KeyCode::Left | KeyCode::Right, KeyModifiers::ALT) => {
+                    self.focus_direction(pressed_key.code);
+                }
+                (KeyCode::Down | KeyCode::Up | KeyCode::Left | KeyCode::Right | KeyCode::PageDown |
+                KeyCode::PageUp | KeyCode::Home | KeyCode::End, KeyModifiers::SHIFT) => {
+                    if self.selection_anchor.is_none() {
+                        self.selection_anchor = Some(self.cursor_position.clone());
                     }
+                    self.move_cursor(pressed_key.code);
+                }
+                (KeyCode::Down | KeyCode::Up | KeyCode::Left | KeyCode::Right | KeyCode::PageDown |
+                KeyCode::PageUp | KeyCode::Home | KeyCode::End, _) => {
+                    self.selection_anchor = None;
+                    self.move_cursor(pressed_key.code);
+                }
+                (KeyCode::Char('c'),KeyModifiers::CONTROL) => self.copy(),
+                (KeyCode::Delete | KeyCode::Backspace | KeyCode::Enter | KeyCode::Char(_), _)
+                if self.document.is_read_only() => {
+                    self.status_message = StatusMessage::from("File is read-only.".to_string());
+                }
+                (KeyCode::Char('x'),KeyModifiers::CONTROL) => {
+                    self.copy();
+                    self.delete_selection();
+                }
+                (KeyCode::Char('v'),KeyModifiers::CONTROL) => self.paste(),
+                (KeyCode::Delete | KeyCode::Backspace, _) if self.selection_range().is_some() => {
+                    self.delete_selection();
+                }
+                (KeyCode::Delete,_) => self.document.delete(&self.cursor_position),
+                (KeyCode::Backspace,_) if self.cursor_position.x > 0 || self.cursor_position.y > 0 => {
+                    let at = self.cursor_position.clone();
+                    self.move_cursor(KeyCode::Left);
+                    self.document.backspace(&at);
                 }
                 (KeyCode::Enter,_) => {
-                    self.document.insert(&self.cursor_position, '\n');
-                    self.move_cursor(KeyCode::Right);
+                    let checkpoint = self.document.edit_checkpoint();
+                    self.delete_selection();
+                    if self.auto_indent == AutoIndent::Off {
+                        self.document.insert(&self.cursor_position, '\n');
+                        self.move_cursor(KeyCode::Right);
+                    } else {
+                        let smart = self.auto_indent == AutoIndent::Smart;
+                        self.cursor_position = self.document.insert_newline(&self.cursor_position, smart);
+                    }
+                    self.document.group_edits(checkpoint);
                 }
                 (KeyCode::Char(c),_) => {
+                    let checkpoint = self.document.edit_checkpoint();
+                    self.delete_selection();
+                    if self.auto_indent == AutoIndent::Smart && self.document.indentation().closes(c) {
+                        self.cursor_position = self.document.dedent(&self.cursor_position);
+                    }
                     self.document.insert(&self.cursor_position, c);
                     self.move_cursor(KeyCode::Right);
+                    self.document.group_edits(checkpoint);
                 }
                 _ => (),
             }
@@ -119,28 +312,254 @@ impl Editor {
             Ok(())
         }
         
+        fn unsaved_buffers(&self) -> usize {
+            let background = self.buffers.iter().filter(|buffer| buffer.document.needs_saving()).count();
+            if self.document.needs_saving() {
+                background.saturating_add(1)
+            } else {
+                background
+            }
+        }
+        
+        fn store_buffer(&mut self) -> Buffer {
+            Buffer {
+                document: mem::take(&mut self.document),
+                cursor_position: mem::take(&mut self.cursor_position),
+                offset: mem::take(&mut self.offset),
+            }
+        }
+        
+        fn load_buffer(&mut self, buffer: Buffer) {
+            self.document = buffer.document;
+            self.cursor_position = buffer.cursor_position;
+            self.offset = buffer.offset;
+        }
+        
+        fn switch_buffer(&mut self, index: usize) {
+            if index == self.current_buffer || index >= self.buffers.len() {
+                return;
+            }
+            self.selection_anchor = None;
+            self.buffers[self.current_buffer] = self.store_buffer();
+            let buffer = mem::take(&mut self.buffers[index]);
+            self.load_buffer(buffer);
+            self.current_buffer = index;
+            self.windows[self.active_window].buffer = index;
+        }
+        
+        fn store_window(&mut self) {
+            let window = &mut self.windows[self.active_window];
+            window.cursor_position = self.cursor_position.clone();
+            window.offset = self.offset.clone();
+        }
+        
+        fn activate_window(&mut self, index: usize) {
+            self.selection_anchor = None;
+            let window = self.windows[index].clone();
+            if window.buffer != self.current_buffer {
+                self.buffers[self.current_buffer].document = mem::take(&mut self.document);
+                self.document = mem::take(&mut self.buffers[window.buffer].document);
+                self.current_buffer = window.buffer;
+            }
+            self.cursor_position = window.cursor_position;
+            self.offset = window.offset;
+            self.active_window = index;
+            self.clamp_cursor();
+            self.scroll();
+        }
+        
+        fn focus_window(&mut self, index: usize) {
+            if index == self.active_window || index >= self.windows.len() {
+                return;
+            }
+            self.store_window();
+            self.activate_window(index);
+        }
+        
+        fn focus_direction(&mut self, key: KeyCode) {
+            let active = self.active_rect();
+            let overlaps_rows = |rect: &Rect| rect.y < active.y + active.height && active.y < rect.y + rect.height;
+            let overlaps_columns = |rect: &Rect| rect.x < active.x + active.width && active.x < rect.x + rect.width;
+            let target = self.layout.rects(self.screen_area())
+                .into_iter()
+                .filter_map(|(index, rect)| {
+                    let distance = match key {
+                        KeyCode::Left if overlaps_rows(&rect) => active.x.checked_sub(rect.x + rect.width),
+                        KeyCode::Right if overlaps_rows(&rect) => rect.x.checked_sub(active.x + active.width),
+                        KeyCode::Up if overlaps_columns(&rect) => active.y.checked_sub(rect.y + rect.height),
+                        KeyCode::Down if overlaps_columns(&rect) => rect.y.checked_sub(active.y + active.height),
+                        _ => None,
+                    };
+                    distance.map(|distance| (distance, index))
+                })
+                .min();
+            if let Some((_, index)) = target {
+                self.focus_window(index);
+            }
+        }
+        
+        fn split_window(&mut self, split: Split) {
+            if !self.active_rect().can_split(split) {
+                self.status_message = StatusMessage::from("Not enough room to split this window.".to_string());
+                return;
+            }
+            self.store_window();
+            let window = self.windows[self.active_window].clone();
+            self.windows.push(window);
+            let index = self.windows.len() - 1;
+            self.layout.split(self.active_window, index, split);
+            self.active_window = index;
+            self.scroll();
+        }
+        
+        fn close_window(&mut self) {
+            if self.windows.len() == 1 {
+                self.status_message = StatusMessage::from("Cannot close the last window.".to_string());
+                return;
+            }
+            self.layout.remove(self.active_window);
+            self.windows.remove(self.active_window);
+            self.activate_window(self.active_window.min(self.windows.len() - 1));
+        }
+        
+        fn resize_window(&mut self, delta: i16) {
+            if self.layout.resize(self.active_window, delta) {
+                self.scroll();
+            }
+        }
+        
+        fn clamp_cursor(&mut self) {
+            let y = self.cursor_position.y.min(self.document.len());
+            let width = self.document.row(y).map_or(0, Row::len);
+            self.cursor_position = Position { x: self.cursor_position.x.min(width), y };
+        }
+        
+        fn buffer_name(document: &Document) -> String {
+            document.file_name.clone().unwrap_or_else(|| "[No Name]".to_string())
+        }
+        
+        fn list_buffers(&mut self) {
+            let names: Vec<String> = self.buffers.iter().enumerate().map(|(index, buffer)| {
+                let document = if index == self.current_buffer {
+                    &self.document
+                } else {
+                    &buffer.document
+                };
+                let modified = if document.needs_saving() { "+" } else { "" };
+                let name = format!("{}:{}{}", index.saturating_add(1), Self::buffer_name(document), modified);
+                if index == self.current_buffer {
+                    format!("[{name}]")
+                } else {
+                    name
+                }
+            }).collect();
+            self.status_message = StatusMessage::from(format!("Buffers: {}", names.join(" ")));
+        }
+        
+        fn open(&mut self) {
+            let Some(file_name) = self.prompt("Open: ", |_, _, _| {}).unwrap_or(None) else {
+                return;
+            };
+            let path = canonical_path(&file_name);
+            let open = (0..self.buffers.len()).find(|index| {
+                let document = if *index == self.current_buffer {
+                    &self.document
+                } else {
+                    &self.buffers[*index].document
+                };
+                document.file_name.as_deref().is_some_and(|name| canonical_path(name) == path)
+            });
+            if let Some(index) = open {
+                self.switch_buffer(index);
+                self.status_message = StatusMessage::from(format!("{file_name} is already open"));
+                return;
+            }
+            let document = match Self::open_document(&file_name) {
+                Ok((document, warning)) => {
+                    self.status_message = StatusMessage::from(warning.unwrap_or_else(|| format!("Opened {file_name}")));
+                    document
+                }
+                Err(_) if !std::path::Path::new(&file_name).exists() => {
+                    self.status_message = StatusMessage::from(format!("New file {file_name}"));
+                    let mut document = Document::default();
+                    document.file_name = Some(file_name);
+                    document
+                }
+                Err(error) => {
+                    self.status_message = StatusMessage::from(error);
+                    return;
+                }
+            };
+            self.buffers.push(Buffer::from(document));
+            self.switch_buffer(self.buffers.len() - 1);
+        }
+        
+        fn close_buffer(&mut self) -> Result<(), std::io::Error> {
+            if self.document.needs_saving() {
+                self.status_message = StatusMessage::from(
+                    "Buffer has unsaved changes. Close anyway? (y/n)".to_string());
+                self.refresh_screen()?;
+                if Self::read_key()?.code != KeyCode::Char('y') {
+                    self.status_message = StatusMessage::from("Close aborted.".to_string());
+                    return Ok(());
+                }
+            }
+            let closed = Self::buffer_name(&self.document);
+            let closed_index = self.current_buffer;
+            if self.buffers.len() == 1 {
+                self.load_buffer(Buffer::default());
+            } else {
+                self.buffers.remove(closed_index);
+                self.current_buffer = closed_index.min(self.buffers.len() - 1);
+                let buffer = mem::take(&mut self.buffers[self.current_buffer]);
+                self.load_buffer(buffer);
+            }
+            for window in &mut self.windows {
+                if window.buffer == closed_index {
+                    window.buffer = self.current_buffer;
+                    window.cursor_position = Position::default();
+                    window.offset = Offset::default();
+                } else if window.buffer > closed_index {
+                    window.buffer -= 1;
+                }
+            }
+            self.status_message = StatusMessage::from(format!("Closed {closed}"));
+            Ok(())
+        }
+        
         fn search(&mut self) {
             let old_position = self.cursor_position.clone();
             let mut direction = SearchDirection::Forward;
-            let query = self.prompt("Search(ESC to cancel and Arrow Keys to navigate): ",
+            let query = self.prompt("Search(ESC to cancel, Arrows to navigate, Alt-r/c/w = regex/case/word): ",
             |editor, key, query|{
                 let mut moved = false;
-                match key {
-                    KeyCode::Right | KeyCode::Down => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Right | KeyCode::Down, _) => {
                         direction = SearchDirection::Forward;
                         editor.move_cursor(KeyCode::Right);
                         moved = true;
                     }
-                    KeyCode::Left | KeyCode::Up => direction = SearchDirection::Backward,
+                    (KeyCode::Left | KeyCode::Up, _) => direction = SearchDirection::Backward,
+                    (KeyCode::Char(_), KeyModifiers::ALT) => editor.toggle_search_option(key.code),
                     _ => direction = SearchDirection::Forward,
                 }
-                if let Some(position) = editor.document.find(&query, &editor.cursor_position, direction) {
-                    editor.cursor_position = position;
-                    editor.scroll();
-                } else if moved {
-                    editor.move_cursor(KeyCode::Left)
+                if let Some(compiled) = editor.compile_query(query) {
+                    let mut wrapped = false;
+                    if let Some((position, did_wrap)) =
+                        editor.document.find_wrapping(&compiled, &editor.cursor_position, direction) {
+                        editor.cursor_position = position;
+                        wrapped = did_wrap;
+                        editor.scroll();
+                    } else if moved {
+                        editor.move_cursor(KeyCode::Left);
+                    }
+                    editor.document.highlight(Some(&compiled));
+                    if !query.is_empty() {
+                        editor.describe_matches(wrapped);
+                    }
+                } else {
+                    editor.document.highlight(None);
                 }
-                editor.document.highlight(Some(&query));
             },)
             .unwrap_or(None);
             if query.is_none() {
@@ -151,25 +570,369 @@ impl Editor {
             
         }
         
+        fn choose_theme(&mut self) {
+            let names: Vec<&str> = self.themes.iter().map(Theme::name).collect();
+            let prompt = format!("Theme ({}): ", names.join(", "));
+            let Some(name) = self.prompt(&prompt, |_, _, _| {}).unwrap_or(None) else {
+                return;
+            };
+            match self.themes.iter().find(|theme| theme.name() == name) {
+                Some(theme) => {
+                    self.theme = theme.adapt(self.terminal.color_support());
+                    self.status_message = StatusMessage::from(format!("Switched to theme {name}"));
+                }
+                None => self.status_message = StatusMessage::from(format!("Unknown theme {name}")),
+            }
+        }
+        
+        fn describe_matches(&mut self, wrapped: bool) {
+            let summary = match self.document.match_index(&self.cursor_position) {
+                (_, 0) => String::from("no matches"),
+                (Some(current), total) => format!("match {current} of {total}"),
+                (None, total) => format!("{total} matches"),
+            };
+            self.prompt_status = format!("{} {}", self.prompt_status, summary).trim_start().to_string();
+            if wrapped {
+                self.prompt_status.push_str(" (search wrapped)");
+            }
+        }
+        
+        fn toggle_search_option(&mut self, key: KeyCode) {
+            match key {
+                KeyCode::Char('r') => self.search_options.toggle_regex(),
+                KeyCode::Char('c') => self.search_options.toggle_case_insensitive(),
+                KeyCode::Char('w') => self.search_options.toggle_whole_word(),
+                _ => (),
+            }
+        }
+        
+        fn compile_query(&mut self, text: &str) -> Option<Query> {
+            match Query::new(text, self.search_options) {
+                Ok(query) => {
+                    self.prompt_status = self.search_options.describe();
+                    Some(query)
+                }
+                Err(error) => {
+                    let message = error.to_string();
+                    let reason = message.lines().last().unwrap_or_default().trim_start_matches("error: ");
+                    self.prompt_status = format!("Invalid pattern: {reason}");
+                    None
+                }
+            }
+        }
+        
+        fn replace(&mut self) -> Result<(), std::io::Error> {
+            if self.document.is_read_only() {
+                self.status_message = StatusMessage::from("File is read-only.".to_string());
+                return Ok(());
+            }
+            let Some(text) = self.prompt("Replace(Alt-r/c/w = regex/case/word): ", |editor, key, query| {
+                if key.modifiers == KeyModifiers::ALT {
+                    editor.toggle_search_option(key.code);
+                }
+                editor.compile_query(query);
+            })? else {
+                return Ok(());
+            };
+            let Some(query) = self.compile_query(&text) else {
+                self.status_message = StatusMessage::from(self.prompt_status.clone());
+                return Ok(());
+            };
+            let Some(replacement) = self.prompt_input(&format!("Replace {text} with: "), |_, _, _| {})? else {
+                return Ok(());
+            };
+            let start = self.cursor_position.clone();
+            let mut position = start.clone();
+            let mut end: Option<Position> = None;
+            let mut replaced = 0;
+            loop {
+                let found = match self.document.find(&query, &position, SearchDirection::Forward) {
+                    Some(found) => found,
+                    None if end.is_none() => {
+                        end = Some(start.clone());
+                        position = Position::default();
+                        continue;
+                    }
+                    None => break,
+                };
+                if end.as_ref().is_some_and(|end| (found.y, found.x) >= (end.y, end.x)) {
+                    break;
+                }
+                self.cursor_position = found.clone();
+                self.scroll();
+                self.document.highlight_match(&found, &query);
+                self.status_message = StatusMessage::from(
+                    "Replace this occurrence? (y)es (n)o (a)ll (q)uit".to_string());
+                self.refresh_screen()?;
+                match Self::read_key()?.code {
+                    KeyCode::Char('y') => {
+                        let len = self.row_len(found.y);
+                        match self.document.replace(&found, &query, &replacement) {
+                            Some(after) => {
+                                position = after;
+                                replaced += 1;
+                                let new_len = self.row_len(found.y);
+                                if let Some(end) = end.as_mut().filter(|end| end.y == found.y) {
+                                    end.x = end.x.saturating_add(new_len).saturating_sub(len);
+                                }
+                            }
+                            None => position = Position { x: found.x.saturating_add(1), y: found.y },
+                        }
+                    }
+                    KeyCode::Char('n') => position = Position { x: found.x.saturating_add(1), y: found.y },
+                    KeyCode::Char('a') => {
+                        let checkpoint = self.document.edit_checkpoint();
+                        replaced += self.document.replace_all(&query, &replacement, &found, end.as_ref());
+                        if end.is_none() {
+                            replaced += self.document.replace_all(&query, &replacement, &Position::default(), Some(&start));
+                        }
+                        self.document.group_edits(checkpoint);
+                        break;
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    _ => (),
+                }
+            }
+            self.document.highlight(None);
+            self.status_message = StatusMessage::from(format!("Replaced {replaced} occurrence(s)"));
+            Ok(())
+        }
+        
+        fn row_len(&self, y: usize) -> usize {
+            self.document.row(y).map_or(0, Row::len)
+        }
+        
+        fn undo(&mut self) {
+            self.selection_anchor = None;
+            if let Some(position) = self.document.undo() {
+                self.cursor_position = position;
+            } else {
+                self.status_message = StatusMessage::from("Nothing to undo.".to_string());
+            }
+        }
+        
+        fn redo(&mut self) {
+            self.selection_anchor = None;
+            if let Some(position) = self.document.redo() {
+                self.cursor_position = position;
+            } else {
+                self.status_message = StatusMessage::from("Nothing to redo.".to_string());
+            }
+        }
+        
+        fn selection_range(&self) -> Option<(Position, Position)> {
+            let anchor = self.selection_anchor.clone()?;
+            let cursor = self.cursor_position.clone();
+            if (anchor.y, anchor.x) == (cursor.y, cursor.x) {
+                return None;
+            }
+            if (anchor.y, anchor.x) < (cursor.y, cursor.x) {
+                Some((anchor, cursor))
+            } else {
+                Some((cursor, anchor))
+            }
+        }
+        
+        fn copy(&mut self) {
+            if let Some((start, end)) = self.selection_range() {
+                self.register = self.document.text_range(&start, &end);
+                let copied = format!("Copied {} character(s)", self.register.chars().count());
+                let result = self.clipboard.copy(&self.register);
+                self.register_is_newer = result.is_err();
+                self.status_message = StatusMessage::from(match result {
+                    Ok(()) => copied,
+                    Err(error) => format!("{} to the register only ({}: {})", copied, self.clipboard.name(), error),
+                });
+            }
+        }
+        
+        fn delete_selection(&mut self) {
+            if let Some((start, end)) = self.selection_range() {
+                self.document.delete_range(&start, &end);
+                self.cursor_position = start;
+            }
+            self.selection_anchor = None;
+        }
+        
+        fn paste(&mut self) {
+            let clipboard = if self.register_is_newer { None } else { self.clipboard.paste() };
+            let text = clipboard
+                .filter(|text| !text.is_empty())
+                .map_or_else(|| self.register.clone(), |text| text.replace("\r\n", "\n"));
+            if text.is_empty() {
+                self.status_message = StatusMessage::from("Nothing to paste.".to_string());
+                return;
+            }
+            let checkpoint = self.document.edit_checkpoint();
+            self.delete_selection();
+            self.cursor_position = self.document.insert_text(&self.cursor_position, &text);
+            self.document.group_edits(checkpoint);
+        }
+        
+        fn toggle_auto_indent(&mut self) {
+            let (auto_indent, description) = match self.auto_indent {
+                AutoIndent::Off => (AutoIndent::Copy, "on"),
+                AutoIndent::Copy => (AutoIndent::Smart, "smart"),
+                AutoIndent::Smart => (AutoIndent::Off, "off"),
+            };
+            self.auto_indent = auto_indent;
+            self.status_message = StatusMessage::from(format!("Auto indent {description}"));
+        }
+        
+        fn toggle_line_numbers(&mut self) {
+            let (line_numbers, description) = match self.line_numbers {
+                LineNumbers::Off => (LineNumbers::Absolute, "on"),
+                LineNumbers::Absolute => (LineNumbers::Relative, "relative"),
+                LineNumbers::Relative => (LineNumbers::Off, "off"),
+            };
+            self.line_numbers = line_numbers;
+            self.scroll();
+            self.status_message = StatusMessage::from(format!("Line numbers {description}"));
+        }
+        
+        fn toggle_wrap(&mut self) {
+            let (wrap, description) = match self.wrap {
+                Wrap::Off => (Wrap::Character, "on"),
+                Wrap::Character => (Wrap::Word, "on (word boundaries)"),
+                Wrap::Word => (Wrap::Off, "off"),
+            };
+            self.wrap = wrap;
+            self.offset.x = 0;
+            self.offset.segment = 0;
+            for window in &mut self.windows {
+                window.offset.x = 0;
+                window.offset.segment = 0;
+            }
+            self.scroll();
+            self.status_message = StatusMessage::from(format!("Soft wrap {description}"));
+        }
+        
+        fn wrap_row(&self, row: &Row, width: usize) -> Vec<(usize, usize)> {
+            let mut segments = row.wrap(width, self.tab_width, self.wrap == Wrap::Word);
+            let end_column = row.display_width(self.tab_width);
+            if let Some(&(start, column)) = segments.last() {
+                if start < row.len() && end_column.saturating_sub(column) >= width.max(1) {
+                    segments.push((row.len(), end_column));
+                }
+            }
+            segments
+        }
+        
+        fn visual_line(&self, position: &Position, width: usize) -> (usize, usize, usize) {
+            let segments = self.document.row(position.y).map_or_else(|| vec![(0, 0)], |row| self.wrap_row(row, width));
+            let segment = segments.iter().rposition(|(start, _)| *start <= position.x).unwrap_or(0);
+            (segment, segments.len(), segments[segment].1)
+        }
+        
+        fn wrapped_distance(&self, width: usize) -> usize {
+            let (segment, _, _) = self.visual_line(&self.cursor_position, width);
+            let mut lines = segment;
+            for row in self.offset.y..self.cursor_position.y {
+                lines += self.document.row(row).map_or(1, |row| self.wrap_row(row, width).len());
+            }
+            lines.saturating_sub(self.offset.segment)
+        }
+        
+        fn scroll_wrapped(&mut self) {
+            let (width, height) = self.view_size();
+            let y = self.cursor_position.y;
+            let (segment, _, _) = self.visual_line(&self.cursor_position, width);
+            let segments = self.document.row(self.offset.y).map_or(1, |row| self.wrap_row(row, width).len());
+            self.offset.segment = self.offset.segment.min(segments.saturating_sub(1));
+            if (y, segment) < (self.offset.y, self.offset.segment) {
+                self.offset.y = y;
+                self.offset.segment = segment;
+                return;
+            }
+            let height = height.max(1);
+            if y.saturating_sub(self.offset.y) < height && self.wrapped_distance(width) < height {
+                return;
+            }
+            let (mut top, mut top_segment) = (y, segment);
+            for _ in 1..height {
+                if top_segment > 0 {
+                    top_segment -= 1;
+                } else if top > 0 {
+                    top -= 1;
+                    top_segment = self.document.row(top).map_or(1, |row| self.wrap_row(row, width).len()).saturating_sub(1);
+                } else {
+                    break;
+                }
+            }
+            self.offset.y = top;
+            self.offset.segment = top_segment;
+        }
+        
+        fn move_visual_line(&mut self, key: KeyCode) {
+            let (width, _) = self.view_size();
+            let Position { x, mut y } = self.cursor_position;
+            let (segment, segments, segment_column) = self.visual_line(&self.cursor_position, width);
+            let column = self.cursor_column().saturating_sub(segment_column);
+            let target = match key {
+                KeyCode::Up if segment > 0 => segment - 1,
+                KeyCode::Up if y > 0 => {
+                    y -= 1;
+                    self.visual_line(&Position { x: usize::MAX, y }, width).0
+                }
+                KeyCode::Down if segment.saturating_add(1) < segments => segment + 1,
+                KeyCode::Down if y < self.document.len() => {
+                    y += 1;
+                    0
+                }
+                _ => return,
+            };
+            let Some(row) = self.document.row(y) else {
+                self.cursor_position = Position { x: 0, y };
+                return;
+            };
+            let lines = self.wrap_row(row, width);
+            let (start, start_column) = lines[target];
+            let end = lines.get(target.saturating_add(1)).map_or(row.len(), |(next, _)| next.saturating_sub(1));
+            let mut x_target = start;
+            while x_target < end && row.display_column(x_target.saturating_add(1), self.tab_width) <= start_column + column {
+                x_target += 1;
+            }
+            if x != x_target || y != self.cursor_position.y {
+                self.cursor_position = Position { x: x_target, y };
+            }
+        }
+        
         fn scroll(&mut self) {
-            let Position {x, y} = self.cursor_position;
-            let width = self.terminal.size().width as usize;
-            let height = self.terminal.size().height as usize;
+            if self.wrap != Wrap::Off {
+                self.scroll_wrapped();
+                return;
+            }
+            let y = self.cursor_position.y;
+            let x = self.cursor_column();
+            let x_end = self.document.row(y).map_or(x, |row| {
+                row.display_column(self.cursor_position.x.saturating_add(1), self.tab_width)
+            }).max(x.saturating_add(1));
+            let (width, height) = self.view_size();
             let offset = &mut self.offset;
             if y < offset.y {
-                offset.y = y
+                offset.y = y;
             } else if y >= offset.y.saturating_add(height) {
                 offset.y = y.saturating_sub(height).saturating_add(1);
             }
             if x < offset.x {
-                offset.x = x
-            } else if x >= offset.x.saturating_add(width) {
-                offset.x = x.saturating_sub(width).saturating_add(1);
+                offset.x = x;
+            } else if x_end > offset.x.saturating_add(width) {
+                offset.x = x_end.saturating_sub(width);
             }
         }
         
+        fn cursor_column(&self) -> usize {
+            self.document
+                .row(self.cursor_position.y)
+                .map_or(0, |row| row.display_column(self.cursor_position.x, self.tab_width))
+        }
+        
         fn move_cursor(&mut self, key: KeyCode) {
-            let terminal_height = self.terminal.size.height as usize;
+            if self.wrap != Wrap::Off && (key == KeyCode::Up || key == KeyCode::Down) {
+                self.move_visual_line(key);
+                return;
+            }
+            let (_, terminal_height) = self.view_size();
             let Position {mut y, mut x} = self.cursor_position;
             let height = self.document.len();
             let mut width = if let Some(row) = self.document.row(y) {
@@ -179,10 +942,8 @@ impl Editor {
             };
             match key {
                 KeyCode::Up => y = y.saturating_sub(1),
-                KeyCode::Down => {
-                    if y < height {
-                        y = y.saturating_add(1);
-                    }
+                KeyCode::Down if y < height => {
+                    y = y.saturating_add(1);
                 }
                 KeyCode::Left => {
                     if x > 0 {
@@ -236,18 +997,18 @@ impl Editor {
         pub fn run(&mut self) {
             loop {
                 if let Err(error) = self.refresh_screen()   {
-                    Self::explode(error);
+                    Self::explode(&error);
                 }
                 if self.should_quit {
                     break;
                 }
                 if let Err(error) = self.process_keypress(){
-                    Self::explode(error);
+                    Self::explode(&error);
                 }
                 
             }
         }
-        fn read_key(&self) -> crossterm::Result<KeyEvent> {
+        fn read_key() -> crossterm::Result<KeyEvent> {
             loop {
                 if event::poll(Duration::from_millis(500))? {
                     if let Event::Key(event) = event::read()? {
@@ -260,48 +1021,70 @@ impl Editor {
             Terminal::cursor_hide();
             Terminal::cursor_position(&Position::default());
             if self.should_quit{
-                Terminal::quit()
+                Terminal::quit();
+            }
+            for (index, rect) in self.layout.rects(self.screen_area()) {
+                self.draw_window(index, &rect);
             }
-            self.draw_rows();
-            self.draw_status_bar();
             self.draw_message_bar();
-            Terminal::cursor_position(&Position {
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
-                y: self.cursor_position.y.saturating_sub(self.offset.y),
-            });
+            let rect = self.text_rect(self.active_window, &self.active_rect());
+            let cursor = if self.wrap == Wrap::Off {
+                Position {
+                    x: self.cursor_column().saturating_sub(self.offset.x),
+                    y: self.cursor_position.y.saturating_sub(self.offset.y),
+                }
+            } else {
+                let (_, _, segment_column) = self.visual_line(&self.cursor_position, rect.width);
+                Position {
+                    x: self.cursor_column().saturating_sub(segment_column),
+                    y: self.wrapped_distance(rect.width),
+                }
+            };
+            Terminal::cursor_position(&Position { x: rect.x + cursor.x, y: rect.y + cursor.y });
             Terminal::cursor_show();
             Terminal::flush()
         }
         
-        fn prompt<C>(&mut self, prompt: &str, mut callback: C) -> std::io::Result<Option<String>> 
+        fn prompt<C>(&mut self, prompt: &str, callback: C) -> std::io::Result<Option<String>> 
         where 
-        C: FnMut(&mut Self, KeyCode, &String), 
+        C: FnMut(&mut Self, KeyEvent, &String), 
+        {
+            Ok(self.prompt_input(prompt, callback)?.filter(|result| !result.is_empty()))
+        }
+        
+        fn prompt_input<C>(&mut self, prompt: &str, mut callback: C) -> std::io::Result<Option<String>> 
+        where 
+        C: FnMut(&mut Self, KeyEvent, &String), 
         {
             let mut result = String::new();
+            self.prompt_status.clear();
             loop {
-                self.status_message = StatusMessage::from(format!("{}{}", prompt, result));
+                let mut message = format!("{prompt}{result}");
+                if !self.prompt_status.is_empty() {
+                    message = format!("{}  {}", message, self.prompt_status);
+                }
+                self.status_message = StatusMessage::from(message);
                 self.refresh_screen()?;
-                let key = self.read_key()?.code; 
-                match key {
-                    KeyCode::Backspace => result.truncate(result.len().saturating_sub(1)),
+                let key = Self::read_key()?; 
+                match key.code {
+                    KeyCode::Backspace => {
+                        result.pop();
+                    }
                     KeyCode::Enter => break,
-                    KeyCode::Char(c) => {
-                        if !c.is_control() {
-                            result.push(c);
-                        }
+                    KeyCode::Char(c) if !c.is_control() && (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                        result.push(c);
                     }
                     KeyCode::Esc => {
-                        result.truncate(0);
-                        break;
+                        self.prompt_status.clear();
+                        self.status_message = StatusMessage::from(String::new());
+                        return Ok(None);
                     }
                     _ => (),
                 }
                 callback(self, key, &result);
             }
+            self.prompt_status.clear();
             self.status_message = StatusMessage::from(String::new());
-            if result.is_empty() {
-                return Ok(None);
-            }
             Ok(Some(result))
         }
         
@@ -315,82 +1098,241 @@ impl Editor {
                 self.document.file_name = new_name;
             }
             
-            if self.document.save().is_ok() {
-                self.status_message = StatusMessage::from("File saved seccessfully".to_string());
-            } else {
-                self.status_message = StatusMessage::from("Error while writing this file!".to_string());
+            match self.document.save() {
+                Ok(None) => self.status_message = StatusMessage::from("File saved seccessfully".to_string()),
+                Ok(Some(warning)) => {
+                    self.status_message = StatusMessage::from(format!("File saved, but syncing its directory failed: {warning}"));
+                }
+                Err(error) => self.status_message = StatusMessage::from(format!("Error while writing this file: {error}")),
             }
             
         }
-        pub fn draw_row(&self, row: &Row) {
-            let width = self.terminal.size.width as usize;
-            let start = self.offset.x;
-            let end = self.offset.x + width;
-            let row = row.render(start, end);
-            println!("{}\r",row)
+        fn screen_area(&self) -> Rect {
+            Rect {
+                x: 0,
+                y: 0,
+                width: self.terminal.size().width as usize,
+                height: (self.terminal.size().height as usize).saturating_add(1),
+            }
         }
-        fn draw_rows(&self) {
-            let screen_rows = self.terminal.size.height as usize;
-            for terminal_row in 0..screen_rows {
-                Terminal::clear_line();
-                if let Some(row) = self.document.row(terminal_row + self.offset.y ) {
-                    self.draw_row(row);
-                } else if terminal_row == screen_rows / 3 && self.document.is_empty() {
-                    self.draw_welcome_message();
+        
+        fn active_rect(&self) -> Rect {
+            self.layout.rects(self.screen_area())
+                .into_iter()
+                .find(|(index, _)| *index == self.active_window)
+                .map_or_else(|| self.screen_area(), |(_, rect)| rect)
+        }
+        
+        fn view_size(&self) -> (usize, usize) {
+            let rect = self.text_rect(self.active_window, &self.active_rect());
+            (rect.width, rect.height.saturating_sub(1))
+        }
+        
+        fn gutter_width(&self, document: &Document) -> usize {
+            if self.line_numbers == LineNumbers::Off {
+                return 0;
+            }
+            let digits = document.len().max(1).to_string().len();
+            SIGN_COLUMNS + digits + 1
+        }
+        
+        fn text_rect(&self, index: usize, rect: &Rect) -> Rect {
+            let (document, _, _) = self.window_view(index);
+            let gutter = self.gutter_width(document).min(rect.width.saturating_sub(1));
+            Rect { x: rect.x + gutter, width: rect.width - gutter, ..*rect }
+        }
+        
+        fn draw_gutter(&self, document: &Document, cursor_y: usize, line: (usize, usize), width: usize) {
+            if width == 0 {
+                return;
+            }
+            let (y, start) = line;
+            let continuation = self.wrap != Wrap::Off && start > 0;
+            let number = if y >= document.len() || continuation {
+                String::new()
+            } else if self.line_numbers == LineNumbers::Relative && y != cursor_y {
+                y.abs_diff(cursor_y).to_string()
+            } else {
+                y.saturating_add(1).to_string()
+            };
+            let signs = " ".repeat(SIGN_COLUMNS);
+            let digits = width.saturating_sub(SIGN_COLUMNS + 1);
+            Terminal::set_style(&self.theme.on_text("line_number"));
+            print!("{signs}{number:>digits$} ");
+            Terminal::reset_style();
+        }
+        
+        fn window_view(&self, index: usize) -> (&Document, &Position, &Offset) {
+            let window = &self.windows[index];
+            let document = if window.buffer == self.current_buffer {
+                &self.document
+            } else {
+                &self.buffers[window.buffer].document
+            };
+            if index == self.active_window {
+                (document, &self.cursor_position, &self.offset)
+            } else {
+                (document, &window.cursor_position, &window.offset)
+            }
+        }
+        
+        pub fn draw_row(&self, row: &Row, start: usize, end: usize, selection: Option<(usize, usize)>) {
+            let row = row.render(start, end, selection, &self.theme, self.tab_width);
+            print!("{row}");
+        }
+        fn screen_lines(&self, document: &Document, offset: &Offset, width: usize, count: usize) -> Vec<(usize, usize, usize)> {
+            if self.wrap == Wrap::Off {
+                return (offset.y..offset.y.saturating_add(count))
+                    .map(|y| (y, offset.x, offset.x.saturating_add(width)))
+                    .collect();
+            }
+            let mut lines = Vec::new();
+            let mut y = offset.y;
+            let mut skip = offset.segment;
+            while lines.len() < count {
+                match document.row(y) {
+                    Some(row) => {
+                        let segments = self.wrap_row(row, width);
+                        skip = skip.min(segments.len().saturating_sub(1));
+                        for (index, (_, start)) in segments.iter().enumerate().skip(skip) {
+                            let end = segments.get(index + 1).map_or(usize::MAX, |(_, column)| *column);
+                            lines.push((y, *start, end));
+                        }
+                    }
+                    None => lines.push((y, 0, width)),
+                }
+                skip = 0;
+                y += 1;
+            }
+            lines.truncate(count);
+            lines
+        }
+        fn row_selection(&self, index: usize, y: usize, row: &Row) -> Option<(usize, usize)> {
+            if self.windows[index].buffer != self.current_buffer {
+                return None;
+            }
+            let (start, end) = self.selection_range()?;
+            if y < start.y || y > end.y {
+                return None;
+            }
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { row.len() };
+            Some((from, to))
+        }
+        fn draw_window(&self, index: usize, rect: &Rect) {
+            let (document, cursor_position, offset) = self.window_view(index);
+            let text = self.text_rect(index, rect);
+            let gutter = text.x - rect.x;
+            let screen_rows = rect.height.saturating_sub(1);
+            let lines = self.screen_lines(document, offset, text.width, screen_rows);
+            for (terminal_row, (y, start, end)) in lines.into_iter().enumerate() {
+                Terminal::cursor_position(&Position { x: rect.x, y: rect.y + terminal_row });
+                self.draw_gutter(document, cursor_position.y, (y, start), gutter);
+                if let Some(row) = document.row(y) {
+                    self.draw_row(row, start, end, self.row_selection(index, y, row));
+                } else if terminal_row == screen_rows / 3 && document.is_empty() {
+                    self.draw_welcome_message(text.width);
                 } else {
-                    println!("~\r");
+                    Terminal::set_style(&self.theme.on_text("tilde"));
+                    print!("~");
+                    Terminal::reset_style();
                 }
-                
+                Terminal::set_style(&self.theme.style("text"));
+                Terminal::clear_until_newline();
+                Terminal::reset_style();
             }
+            if rect.x + rect.width < self.screen_area().width {
+                Terminal::set_style(&self.theme.on_text("separator"));
+                for terminal_row in rect.y..rect.y + rect.height {
+                    Terminal::cursor_position(&Position { x: rect.x + rect.width, y: terminal_row });
+                    print!("|");
+                }
+                Terminal::reset_style();
+            }
+            self.draw_status_bar(index, rect);
         }
-        fn draw_welcome_message(&self) {            
-            let mut welcome_message = format!("Welcome to my text editor");            
-            let width = self.terminal.size.width as usize;            
+        fn draw_welcome_message(&self, width: usize) {            
+            let mut welcome_message = "Welcome to my text editor".to_string();            
             let len = welcome_message.len();            
             let padding = width.saturating_sub(len) / 2;            
             let spaces = " ".repeat(padding.saturating_sub(1));            
-            welcome_message = format!("~{}{}", spaces, welcome_message);            
-            welcome_message.truncate(width);            
-            println!("{}\r", welcome_message);            
+            welcome_message = format!("~{spaces}{welcome_message}");            
+            let welcome_message = truncate_to_width(&welcome_message, width);            
+            Terminal::set_style(&self.theme.on_text("tilde"));
+            print!("{welcome_message}");            
+            Terminal::reset_style();
         }
         
-        fn draw_status_bar(&self) {
+        fn draw_status_bar(&self, index: usize, rect: &Rect) {
             let mut status;
-            let width = self.terminal.size().width as usize;
-            let modified_indicator = if self.document.needs_saving() {
+            let width = rect.width;
+            let (document, cursor_position, _) = self.window_view(index);
+            let modified_indicator = if document.is_read_only() {
+                " [read-only]"
+            } else if document.needs_saving() {
                 " (modified)"
             } else {
                 ""
             };
             
             let mut file_name = "[No Name]".to_string();
-            if let Some(name) = &self.document.file_name {
-                file_name = name.clone();
-                file_name.truncate(20)
+            if let Some(name) = &document.file_name {
+                file_name.clone_from(name);
+                file_name = truncate_to_width(&file_name, 20);
             }
-            status = format!("{} - {} lines{}",file_name, self.document.len(),modified_indicator);
+            status = format!("[{}/{}] {} - {} lines{}", self.windows[index].buffer.saturating_add(1), self.buffers.len(),
+                file_name, document.len(),modified_indicator);
+            let final_newline = if document.has_final_newline() {
+                ""
+            } else {
+                " noeol"
+            };
             let line_indicator = format!(
-                "{} | {}/{}", self.document.file_type(), self.cursor_position.y.saturating_add(1),self.document.len());
-                let len = status.len() + line_indicator.len();
+                "{} | {} | {}{} | {}/{}", document.file_type(), document.encoding().name(),
+                document.line_ending().name(), final_newline,
+                cursor_position.y.saturating_add(1),document.len());
+                let len = status.width() + line_indicator.width();
                 if width > len {
                     status.push_str(&" ".repeat(width-len));
                 }
-                status = format!("{}{}",status,line_indicator);
-                status.truncate(width);
-                Terminal::set_colors(Colors::new(STATUS_FG_COLOR, STATUS_BG_COLOR));
-                println!("{}\r",status);
-                Terminal::reset_colors();
+                status = format!("{status}{line_indicator}");
+                status = truncate_to_width(&status, width);
+                Terminal::cursor_position(&Position { x: rect.x, y: rect.y + rect.height.saturating_sub(1) });
+                if index == self.active_window {
+                    Terminal::set_style(&self.theme.style("status_bar"));
+                } else {
+                    Terminal::set_style(&self.theme.style("status_bar_inactive"));
+                }
+                print!("{status}");
+                Terminal::reset_style();
             }
             
             fn draw_message_bar(&self) {
+                Terminal::cursor_position(&Position { x: 0, y: self.screen_area().height });
+                Terminal::set_style(&self.theme.on_text("message_bar"));
                 Terminal::clear_line();
                 let message = &self.status_message;
-                if Instant::now() - message.time < Duration::new(5,0) {
-                    let mut text = message.text.clone();
-                    text.truncate(self.terminal.size().width as usize);
-                    print!("{}",text);
+                if message.time.elapsed() < Duration::new(5,0) {
+                    let text = truncate_to_width(&message.text, self.terminal.size().width as usize);
+                    print!("{text}");
                 }
+                Terminal::reset_style();
             }
             
             
-        }
\ No newline at end of file
+        }
+
+fn canonical_path(file_name: &str) -> PathBuf {
+    fs::canonicalize(file_name)
+        .or_else(|_| path::absolute(file_name))
+        .unwrap_or_else(|_| PathBuf::from(file_name))
+}
+
+fn summarize(kind: &str, errors: &[String]) -> Option<String> {
+    let first = errors.first()?;
+    if errors.len() > 1 {
+        Some(format!("{}: {} (and {} more)", kind, first, errors.len() - 1))
+    } else {
+        Some(format!("{kind}: {first}"))
+    }
+}
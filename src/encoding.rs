@@ -0,0 +1,209 @@
+use std::io::{Error, ErrorKind};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Encoding {
+    Utf8 { bom: bool },
+    Utf16Le { bom: bool },
+    Utf16Be { bom: bool },
+    Latin1,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Utf8 { bom: false }
+    }
+}
+
+impl Encoding {
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        let with_bom = [
+            Encoding::Utf8 { bom: true },
+            Encoding::Utf16Le { bom: true },
+            Encoding::Utf16Be { bom: true },
+        ];
+        if let Some(encoding) = with_bom.into_iter().find(|encoding| bytes.starts_with(encoding.bom())) {
+            if encoding.decodes(&bytes[encoding.bom().len()..]) {
+                return Some(encoding);
+            }
+        }
+        if let Some(encoding) = Self::detect_utf16_without_bom(bytes) {
+            return Some(encoding);
+        }
+        if bytes.contains(&0) {
+            return None;
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return Some(Encoding::Utf8 { bom: false });
+        }
+        let control = bytes
+            .iter()
+            .filter(|byte| byte.is_ascii_control() && !byte.is_ascii_whitespace() && **byte != 0x1B)
+            .count();
+        if control.saturating_mul(10) > bytes.len() {
+            return None;
+        }
+        Some(Encoding::Latin1)
+    }
+
+    fn detect_utf16_without_bom(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let even_zeros = bytes.iter().step_by(2).filter(|byte| **byte == 0).count();
+        let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count();
+        let units = bytes.len() / 2;
+        let encoding = if odd_zeros.saturating_mul(2) > units && even_zeros == 0 {
+            Encoding::Utf16Le { bom: false }
+        } else if even_zeros.saturating_mul(2) > units && odd_zeros == 0 {
+            Encoding::Utf16Be { bom: false }
+        } else {
+            return None;
+        };
+        if encoding.decodes(bytes) {
+            Some(encoding)
+        } else {
+            None
+        }
+    }
+
+    fn decodes(self, bytes: &[u8]) -> bool {
+        match self {
+            Encoding::Utf8 { .. } => std::str::from_utf8(bytes).is_ok(),
+            Encoding::Utf16Le { .. } | Encoding::Utf16Be { .. } => {
+                bytes.len().is_multiple_of(2) && char::decode_utf16(self.code_units(bytes)).all(|c| c.is_ok())
+            }
+            Encoding::Latin1 => true,
+        }
+    }
+
+    #[must_use]
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        match self {
+            Encoding::Utf8 { .. } => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Utf16Le { .. } | Encoding::Utf16Be { .. } => {
+                let units = self.code_units(bytes);
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Encoding::Latin1 => bytes.iter().map(|byte| char::from(*byte)).collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn decode_binary(bytes: &[u8]) -> String {
+        String::from_utf8_lossy(bytes)
+            .chars()
+            .map(|c| if c.is_control() && c != '\n' && c != '\t' { '.' } else { c })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 { bom: true } => UTF8_BOM,
+            Encoding::Utf16Le { bom: true } => UTF16LE_BOM,
+            Encoding::Utf16Be { bom: true } => UTF16BE_BOM,
+            _ => &[],
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Encoding::Utf8 { .. } => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf16Le { .. } => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be { .. } => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(u32::from(c)).map_err(|_| {
+                        Error::new(ErrorKind::InvalidData, format!("'{c}' cannot be encoded as Latin-1"))
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 { bom: false } => "UTF-8",
+            Encoding::Utf8 { bom: true } => "UTF-8 BOM",
+            Encoding::Utf16Le { bom: false } => "UTF-16LE",
+            Encoding::Utf16Le { bom: true } => "UTF-16LE BOM",
+            Encoding::Utf16Be { bom: false } => "UTF-16BE",
+            Encoding::Utf16Be { bom: true } => "UTF-16BE BOM",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    fn code_units(self, bytes: &[u8]) -> Vec<u16> {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| {
+                if let Encoding::Utf16Be { .. } = self {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> Encoding {
+        let encoding = Encoding::detect(bytes).unwrap();
+        let text = encoding.decode(bytes);
+        let mut encoded = encoding.bom().to_vec();
+        encoded.extend(encoding.encode(&text).unwrap());
+        assert_eq!(encoded, bytes);
+        encoding
+    }
+
+    #[test]
+    fn utf8_round_trips() {
+        assert!(round_trip("caf\u{e9} \u{1f600}\n".as_bytes()) == Encoding::Utf8 { bom: false });
+        assert!(round_trip(b"\xEF\xBB\xBFhello\n") == Encoding::Utf8 { bom: true });
+    }
+
+    #[test]
+    fn utf16_round_trips() {
+        let text = "h\u{e9}llo \u{1f600}\n";
+        let mut le = UTF16LE_BOM.to_vec();
+        le.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        assert!(round_trip(&le) == Encoding::Utf16Le { bom: true });
+        let be: Vec<u8> = "hello\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert!(round_trip(&be) == Encoding::Utf16Be { bom: false });
+    }
+
+    #[test]
+    fn latin1_round_trips() {
+        assert!(round_trip(b"caf\xE9\n") == Encoding::Latin1);
+    }
+
+    #[test]
+    fn invalid_utf8_after_bom_keeps_its_bytes() {
+        let bytes = b"\xEF\xBB\xBFcaf\xE9\n";
+        assert!(round_trip(bytes) == Encoding::Latin1);
+    }
+
+    #[test]
+    fn binary_is_not_text() {
+        assert!(Encoding::detect(b"\x7FELF\x02\x01\x00\x00").is_none());
+    }
+
+    #[test]
+    fn latin1_rejects_wide_characters() {
+        assert!(Encoding::Latin1.encode("\u{1f600}").is_err());
+    }
+}
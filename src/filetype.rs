@@ -1,58 +1,731 @@
+use std::path::Path;
+
+use crate::IndentSettings;
+use crate::SyntaxDefinition;
+
 pub struct FileType {
     name: String,
     hl_options: HighlightingOptions,
+    indentation: Indentation,
+}
+
+#[derive(Clone)]
+pub struct Indentation {
+    tabs: bool,
+    width: usize,
+    openers: Vec<char>,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct HighlightingOptions {
     numbers: bool,
     strings: bool,
     characters: bool,
     comments: bool,
+    multiline_comments: bool,
+    raw_strings: bool,
+    lifetimes: bool,
+    macros: bool,
+    nested_comments: bool,
+    triple_quotes: bool,
+    line_comment: String,
+    block_comment: (String, String),
+    string_delimiters: Vec<char>,
+    raw_delimiters: Vec<char>,
+    multiline_strings: Vec<char>,
+    number_prefixes: Vec<String>,
+    number_separator: Option<char>,
+    number_suffixes: Vec<String>,
+    primary_keywords: Vec<String>,
+    secondary_keywords: Vec<String>,
 }
 
 impl HighlightingOptions {
-    pub fn numbers(self) -> bool {
+    fn c_like() -> Self {
+        Self {
+            numbers: true,
+            strings: true,
+            characters: true,
+            comments: true,
+            multiline_comments: true,
+            line_comment: String::from("//"),
+            block_comment: (String::from("/*"), String::from("*/")),
+            string_delimiters: vec!['"'],
+            ..Self::default()
+        }
+    }
+
+    fn script_like() -> Self {
+        Self {
+            numbers: true,
+            strings: true,
+            comments: true,
+            line_comment: String::from("#"),
+            string_delimiters: vec!['"', '\''],
+            ..Self::default()
+        }
+    }
+
+    fn javascript(extra_keywords: &[&str], extra_types: &[&str]) -> Self {
+        let mut primary_keywords = words(&[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+            "delete", "do", "else", "export", "extends", "false", "finally", "for", "from", "function", "if",
+            "import", "in", "instanceof", "let", "new", "null", "of", "return", "static", "super", "switch",
+            "this", "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "yield",
+        ]);
+        primary_keywords.extend(words(extra_keywords));
+        let mut secondary_keywords = words(&[
+            "Array", "Boolean", "Date", "Error", "JSON", "Map", "Math", "Number", "Object", "Promise",
+            "RegExp", "Set", "String", "Symbol", "console",
+        ]);
+        secondary_keywords.extend(words(extra_types));
+        Self {
+            characters: false,
+            number_prefixes: words(&["0x", "0o", "0b"]),
+            number_separator: Some('_'),
+            number_suffixes: words(&["n"]),
+            string_delimiters: vec!['"', '\'', '`'],
+            multiline_strings: vec!['`'],
+            primary_keywords,
+            secondary_keywords,
+            ..Self::c_like()
+        }
+    }
+
+    #[must_use]
+    pub fn numbers(&self) -> bool {
         self.numbers
     }
 
-    pub fn strings(self) -> bool {
+    #[must_use]
+    pub fn strings(&self) -> bool {
         self.strings
     }
 
-    pub fn characters(self) -> bool {
+    #[must_use]
+    pub fn characters(&self) -> bool {
         self.characters
     }
 
-    pub fn comments(self) -> bool {
+    #[must_use]
+    pub fn comments(&self) -> bool {
         self.comments
     }
+
+    #[must_use]
+    pub fn multiline_comments(&self) -> bool {
+        self.multiline_comments
+    }
+
+    #[must_use]
+    pub fn raw_strings(&self) -> bool {
+        self.raw_strings
+    }
+
+    #[must_use]
+    pub fn lifetimes(&self) -> bool {
+        self.lifetimes
+    }
+
+    #[must_use]
+    pub fn macros(&self) -> bool {
+        self.macros
+    }
+
+    #[must_use]
+    pub fn nested_comments(&self) -> bool {
+        self.nested_comments
+    }
+
+    #[must_use]
+    pub fn triple_quotes(&self) -> bool {
+        self.triple_quotes
+    }
+
+    #[must_use]
+    pub fn line_comment(&self) -> &str {
+        &self.line_comment
+    }
+
+    #[must_use]
+    pub fn block_comment(&self) -> (&str, &str) {
+        (&self.block_comment.0, &self.block_comment.1)
+    }
+
+    #[must_use]
+    pub fn string_delimiters(&self) -> &Vec<char> {
+        &self.string_delimiters
+    }
+
+    #[must_use]
+    pub fn raw_delimiters(&self) -> &Vec<char> {
+        &self.raw_delimiters
+    }
+
+    #[must_use]
+    pub fn multiline_strings(&self) -> &Vec<char> {
+        &self.multiline_strings
+    }
+
+    #[must_use]
+    pub fn number_prefixes(&self) -> &Vec<String> {
+        &self.number_prefixes
+    }
+
+    #[must_use]
+    pub fn number_separator(&self) -> Option<char> {
+        self.number_separator
+    }
+
+    #[must_use]
+    pub fn number_suffixes(&self) -> &Vec<String> {
+        &self.number_suffixes
+    }
+
+    #[must_use]
+    pub fn primary_keywords(&self) -> &Vec<String> {
+        &self.primary_keywords
+    }
+
+    #[must_use]
+    pub fn secondary_keywords(&self) -> &Vec<String> {
+        &self.secondary_keywords
+    }
+}
+impl Default for Indentation {
+    fn default() -> Self {
+        Self {
+            tabs: false,
+            width: 4,
+            openers: Vec::new(),
+        }
+    }
 }
+
+impl Indentation {
+    fn brackets() -> Self {
+        Self {
+            openers: vec!['{', '(', '['],
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn unit(&self) -> String {
+        if self.tabs {
+            String::from("\t")
+        } else {
+            " ".repeat(self.width)
+        }
+    }
+
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[must_use]
+    pub fn opens(&self, c: char) -> bool {
+        self.openers.contains(&c)
+    }
+
+    #[must_use]
+    pub fn closes(&self, c: char) -> bool {
+        self.openers.iter().any(|opener| closing_bracket(*opener) == Some(c))
+    }
+}
+
 impl Default for FileType {
     fn default() -> Self {
         Self {
             name: String::from("No filetype"),
             hl_options: HighlightingOptions::default(),
+            indentation: Indentation::default(),
         }
     }
 }
 
 impl FileType {
+    #[must_use]
     pub fn name(&self) -> String{
         self.name.clone()
     }
 
+    pub const SCANNED_LINES: usize = 5;
+
+    #[must_use]
     pub fn from(file_name: &str) -> Self {
-        if file_name.ends_with(".rs") {
-            return Self {
-                name: String::from("Rust"),
-                hl_options: HighlightingOptions { numbers: true, strings: true, characters: true, comments: true, },
-            };
+        if let Some(file_type) = Self::from_file_name(file_name) {
+            return file_type;
+        }
+        let extension = Path::new(file_name).extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        if let Some(definition) = SyntaxDefinition::all().iter().find(|definition| definition.matches_extension(extension)) {
+            return Self::from_definition(definition);
+        }
+        match extension {
+            "rs" => Self::rust(),
+            "c" | "h" => Self::c(),
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Self::cpp(),
+            "py" | "pyw" => Self::python(),
+            "go" => Self::go(),
+            "js" | "mjs" | "cjs" | "jsx" => Self::javascript(),
+            "ts" | "mts" | "cts" | "tsx" => Self::typescript(),
+            "toml" => Self::toml(),
+            "yml" | "yaml" => Self::yaml(),
+            "json" => Self::json(),
+            "md" | "markdown" => Self::markdown(),
+            "sh" | "bash" | "zsh" => Self::shell(),
+            "mk" => Self::makefile(),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn detect(file_name: &str, lines: &[&str]) -> Self {
+        lines
+            .iter()
+            .find_map(|line| modeline(line))
+            .and_then(Self::from_language)
+            .or_else(|| Self::from_file_name(file_name))
+            .or_else(|| lines.first().and_then(|line| shebang(line)).and_then(Self::from_language))
+            .unwrap_or_else(|| Self::from(file_name))
+    }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let base_name = Path::new(file_name).file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if let Some(definition) = SyntaxDefinition::all().iter().find(|definition| definition.matches_file_name(base_name)) {
+            return Some(Self::from_definition(definition));
+        }
+        match base_name {
+            "Makefile" | "makefile" | "GNUmakefile" => Some(Self::makefile()),
+            "Dockerfile" | "Containerfile" => Some(Self::dockerfile()),
+            "Cargo.lock" | "Pipfile" | "poetry.lock" => Some(Self::toml()),
+            ".bashrc" | ".bash_profile" | ".bash_logout" | ".profile" | ".zshrc" | ".zprofile" | ".zshenv"
+            | "PKGBUILD" => Some(Self::shell()),
+            _ if base_name.starts_with("Dockerfile.") => Some(Self::dockerfile()),
+            _ => None,
+        }
+    }
+
+    fn from_language(language: &str) -> Option<Self> {
+        if let Some(definition) = SyntaxDefinition::all().iter().find(|definition| definition.matches_language(language)) {
+            return Some(Self::from_definition(definition));
+        }
+        match language.to_lowercase().as_str() {
+            "rust" | "rs" => Some(Self::rust()),
+            "c" => Some(Self::c()),
+            "cpp" | "c++" | "cxx" => Some(Self::cpp()),
+            "python" | "py" => Some(Self::python()),
+            "go" | "golang" => Some(Self::go()),
+            "javascript" | "js" | "node" | "deno" | "bun" => Some(Self::javascript()),
+            "typescript" | "ts" | "ts-node" => Some(Self::typescript()),
+            "toml" | "conf-toml" => Some(Self::toml()),
+            "yaml" | "yml" => Some(Self::yaml()),
+            "json" => Some(Self::json()),
+            "markdown" | "md" => Some(Self::markdown()),
+            "sh" | "bash" | "zsh" | "ksh" | "dash" | "shell" | "shell-script" => Some(Self::shell()),
+            "make" | "makefile" => Some(Self::makefile()),
+            "dockerfile" | "docker" => Some(Self::dockerfile()),
+            _ => None,
         }
-        Self::default()
     }
 
-    pub fn highlighting_options(&self) -> HighlightingOptions {
-        self.hl_options
+    fn from_definition(definition: &SyntaxDefinition) -> Self {
+        let (block_start, block_end) = definition.block_comment.clone().unwrap_or_default();
+        Self {
+            name: definition.name.clone(),
+            hl_options: HighlightingOptions {
+                numbers: definition.numbers,
+                number_prefixes: definition.number_prefixes.clone(),
+                number_separator: definition.number_separator,
+                number_suffixes: definition.number_suffixes.clone(),
+                strings: !definition.string_delimiters.is_empty(),
+                characters: definition.characters,
+                comments: definition.line_comment.is_some(),
+                multiline_comments: definition.block_comment.is_some(),
+                nested_comments: definition.nested_comments,
+                line_comment: definition.line_comment.clone().unwrap_or_default(),
+                block_comment: (block_start, block_end),
+                string_delimiters: definition.string_delimiters.clone(),
+                multiline_strings: definition.multiline_strings.clone(),
+                primary_keywords: definition.primary_keywords.clone(),
+                secondary_keywords: definition.secondary_keywords.clone(),
+                ..HighlightingOptions::default()
+            },
+            indentation: Indentation {
+                tabs: definition.indent_tabs,
+                width: definition.indent_width.unwrap_or(4),
+                openers: definition.indent_after.clone(),
+            },
+        }
     }
-}
\ No newline at end of file
+
+    fn rust() -> Self {
+        Self {
+            name: String::from("Rust"),
+            hl_options: HighlightingOptions {
+                raw_strings: true,
+                multiline_strings: vec!['"'],
+                lifetimes: true,
+                macros: true,
+                nested_comments: true,
+                number_prefixes: words(&["0x", "0o", "0b"]),
+                number_separator: Some('_'),
+                number_suffixes: words(&[
+                    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+                    "f64",
+                ]),
+                primary_keywords: words(&[
+                    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+                    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                    "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                    "trait", "true", "type", "unsafe", "use", "where", "while",
+                ]),
+                secondary_keywords: words(&[
+                    "bool", "char", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+                    "u128", "usize", "f32", "f64", "str", "String", "Vec", "Option", "Some", "None",
+                    "Result", "Ok", "Err", "Box",
+                ]),
+                ..HighlightingOptions::c_like()
+            },
+            indentation: Indentation::brackets(),
+        }
+    }
+
+    fn c() -> Self {
+        Self {
+            name: String::from("C"),
+            hl_options: HighlightingOptions {
+                number_prefixes: words(&["0x", "0b"]),
+                number_separator: Some('\''),
+                number_suffixes: words(&["u", "l", "ul", "lu", "ll", "ull", "llu", "f"]),
+                primary_keywords: words(&[
+                    "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for",
+                    "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static", "struct",
+                    "switch", "typedef", "union", "volatile", "while", "NULL",
+                ]),
+                secondary_keywords: words(&[
+                    "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "bool",
+                    "size_t", "ssize_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t",
+                    "uint32_t", "uint64_t", "FILE",
+                ]),
+                ..HighlightingOptions::c_like()
+            },
+            indentation: Indentation::brackets(),
+        }
+    }
+
+    fn cpp() -> Self {
+        Self {
+            name: String::from("C++"),
+            hl_options: HighlightingOptions {
+                number_prefixes: words(&["0x", "0b"]),
+                number_separator: Some('\''),
+                number_suffixes: words(&["u", "l", "ul", "lu", "ll", "ull", "llu", "f"]),
+                primary_keywords: words(&[
+                    "break", "case", "catch", "class", "const", "constexpr", "continue", "default", "delete",
+                    "do", "else", "enum", "explicit", "extern", "false", "for", "friend", "goto", "if",
+                    "inline", "namespace", "new", "noexcept", "nullptr", "operator", "private", "protected",
+                    "public", "return", "sizeof", "static", "struct", "switch", "template", "this", "throw",
+                    "true", "try", "typedef", "typename", "union", "using", "virtual", "volatile", "while",
+                ]),
+                secondary_keywords: words(&[
+                    "auto", "bool", "char", "double", "float", "int", "long", "short", "signed", "unsigned",
+                    "void", "size_t", "std", "string", "vector", "map", "unique_ptr", "shared_ptr",
+                ]),
+                ..HighlightingOptions::c_like()
+            },
+            indentation: Indentation::brackets(),
+        }
+    }
+
+    fn python() -> Self {
+        Self {
+            name: String::from("Python"),
+            hl_options: HighlightingOptions {
+                triple_quotes: true,
+                number_prefixes: words(&["0x", "0o", "0b"]),
+                number_separator: Some('_'),
+                number_suffixes: words(&["j"]),
+                primary_keywords: words(&[
+                    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+                    "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+                    "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+                    "try", "while", "with", "yield", "self",
+                ]),
+                secondary_keywords: words(&[
+                    "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple", "len",
+                    "print", "range", "super", "type",
+                ]),
+                ..HighlightingOptions::script_like()
+            },
+            indentation: Indentation {
+                openers: vec![':', '{', '(', '['],
+                ..Indentation::default()
+            },
+        }
+    }
+
+    fn go() -> Self {
+        Self {
+            name: String::from("Go"),
+            hl_options: HighlightingOptions {
+                string_delimiters: vec!['"', '`'],
+                raw_delimiters: vec!['`'],
+                multiline_strings: vec!['`'],
+                number_prefixes: words(&["0x", "0o", "0b"]),
+                number_separator: Some('_'),
+                number_suffixes: words(&["i"]),
+                primary_keywords: words(&[
+                    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough",
+                    "for", "func", "go", "goto", "if", "import", "interface", "map", "package", "range",
+                    "return", "select", "struct", "switch", "type", "var", "true", "false", "nil",
+                ]),
+                secondary_keywords: words(&[
+                    "bool", "byte", "complex64", "complex128", "error", "float32", "float64", "int", "int8",
+                    "int16", "int32", "int64", "rune", "string", "uint", "uint8", "uint16", "uint32",
+                    "uint64", "uintptr", "any",
+                ]),
+                ..HighlightingOptions::c_like()
+            },
+            indentation: Indentation {
+                tabs: true,
+                ..Indentation::brackets()
+            },
+        }
+    }
+
+    fn javascript() -> Self {
+        Self {
+            name: String::from("JavaScript"),
+            hl_options: HighlightingOptions::javascript(&[], &[]),
+            indentation: Indentation {
+                width: 2,
+                ..Indentation::brackets()
+            },
+        }
+    }
+
+    fn typescript() -> Self {
+        Self {
+            name: String::from("TypeScript"),
+            hl_options: HighlightingOptions::javascript(
+                &[
+                    "abstract", "as", "declare", "enum", "implements", "interface", "keyof", "namespace",
+                    "private", "protected", "public", "readonly", "type",
+                ],
+                &["any", "boolean", "never", "number", "string", "unknown", "bigint", "object"],
+            ),
+            indentation: Indentation {
+                width: 2,
+                ..Indentation::brackets()
+            },
+        }
+    }
+
+    fn toml() -> Self {
+        Self {
+            name: String::from("TOML"),
+            hl_options: HighlightingOptions {
+                primary_keywords: words(&["true", "false"]),
+                ..HighlightingOptions::script_like()
+            },
+            indentation: Indentation::brackets(),
+        }
+    }
+
+    fn yaml() -> Self {
+        Self {
+            name: String::from("YAML"),
+            hl_options: HighlightingOptions {
+                primary_keywords: words(&["true", "false", "null", "yes", "no", "on", "off"]),
+                ..HighlightingOptions::script_like()
+            },
+            indentation: Indentation {
+                width: 2,
+                openers: vec![':'],
+                ..Indentation::default()
+            },
+        }
+    }
+
+    fn json() -> Self {
+        Self {
+            name: String::from("JSON"),
+            hl_options: HighlightingOptions {
+                numbers: true,
+                strings: true,
+                string_delimiters: vec!['"'],
+                primary_keywords: words(&["true", "false", "null"]),
+                ..HighlightingOptions::default()
+            },
+            indentation: Indentation {
+                width: 2,
+                ..Indentation::brackets()
+            },
+        }
+    }
+
+    fn markdown() -> Self {
+        Self {
+            name: String::from("Markdown"),
+            hl_options: HighlightingOptions {
+                multiline_comments: true,
+                block_comment: (String::from("<!--"), String::from("-->")),
+                ..HighlightingOptions::default()
+            },
+            indentation: Indentation::default(),
+        }
+    }
+
+    fn shell() -> Self {
+        Self {
+            name: String::from("Shell"),
+            hl_options: HighlightingOptions {
+                primary_keywords: words(&[
+                    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+                    "in", "function", "return", "local", "export", "readonly", "select", "break", "continue",
+                ]),
+                secondary_keywords: words(&[
+                    "echo", "printf", "cd", "exit", "set", "unset", "shift", "source", "test", "read", "eval",
+                    "exec", "trap",
+                ]),
+                multiline_strings: vec!['"', '\''],
+                ..HighlightingOptions::script_like()
+            },
+            indentation: Indentation::brackets(),
+        }
+    }
+
+    fn dockerfile() -> Self {
+        Self {
+            name: String::from("Dockerfile"),
+            hl_options: HighlightingOptions {
+                primary_keywords: words(&[
+                    "FROM", "AS", "RUN", "CMD", "LABEL", "EXPOSE", "ENV", "ADD", "COPY", "ENTRYPOINT", "VOLUME",
+                    "USER", "WORKDIR", "ARG", "ONBUILD", "STOPSIGNAL", "HEALTHCHECK", "SHELL", "MAINTAINER",
+                ]),
+                ..HighlightingOptions::script_like()
+            },
+            indentation: Indentation::default(),
+        }
+    }
+
+    fn makefile() -> Self {
+        Self {
+            name: String::from("Makefile"),
+            hl_options: HighlightingOptions {
+                comments: true,
+                line_comment: String::from("#"),
+                primary_keywords: words(&[
+                    "ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "define", "endef",
+                    "export", "override", "vpath",
+                ]),
+                ..HighlightingOptions::default()
+            },
+            indentation: Indentation {
+                tabs: true,
+                openers: vec![':'],
+                ..Indentation::default()
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn highlighting_options(&self) -> &HighlightingOptions {
+        &self.hl_options
+    }
+
+    #[must_use]
+    pub fn indentation(&self) -> Indentation {
+        let mut indentation = self.indentation.clone();
+        if let Some(settings) = IndentSettings::find(&self.name) {
+            indentation.tabs = settings.tabs.unwrap_or(indentation.tabs);
+            indentation.width = settings.width.unwrap_or(indentation.width);
+            if let Some(after) = &settings.after {
+                indentation.openers.clone_from(after);
+            }
+        }
+        indentation
+    }
+}
+
+fn closing_bracket(opener: char) -> Option<char> {
+    match opener {
+        '{' => Some('}'),
+        '(' => Some(')'),
+        '[' => Some(']'),
+        _ => None,
+    }
+}
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| (*word).to_string()).collect()
+}
+
+fn shebang(line: &str) -> Option<&str> {
+    let mut parts = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = parts.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = parts.find(|part| !part.starts_with('-') && !part.contains('='))?;
+    }
+    Some(interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+}
+
+fn modeline(line: &str) -> Option<&str> {
+    if let Some(start) = line.find("-*-") {
+        let inner = &line[start + 3..];
+        let inner = &inner[..inner.find("-*-")?];
+        if !inner.contains(':') {
+            return Some(inner.trim());
+        }
+        return inner.split(';').find_map(|setting| {
+            let (key, value) = setting.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim())
+        });
+    }
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| *index == 0 || line[..*index].ends_with(char::is_whitespace))
+            .map(|(index, _)| index + marker.len())
+    })?;
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|setting| {
+            let (key, value) = setting.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax").then(|| value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shebang_names_the_interpreter() {
+        assert_eq!(shebang("#!/usr/bin/env python3"), Some("python"));
+        assert_eq!(shebang("#!/bin/sh -e"), Some("sh"));
+        assert_eq!(shebang("#!/usr/bin/env -S LANG=C node --harmony"), Some("node"));
+        assert_eq!(shebang("#!/usr/bin/python3.11"), Some("python"));
+        assert_eq!(shebang("# not a shebang"), None);
+        assert_eq!(shebang("#!"), None);
+    }
+
+    #[test]
+    fn modeline_names_the_file_type() {
+        assert_eq!(modeline("// vim: set ft=rust:"), Some("rust"));
+        assert_eq!(modeline("/* vim: filetype=c */"), Some("c"));
+        assert_eq!(modeline("# -*- mode: python; coding: utf-8 -*-"), Some("python"));
+        assert_eq!(modeline("; -*- lisp -*-"), Some("lisp"));
+        assert_eq!(modeline("# vim: set ts=4 sw=4:"), None);
+        assert_eq!(modeline("let vim = 1;"), None);
+        assert_eq!(modeline("envim: ft=rust"), None);
+    }
+
+    #[test]
+    fn detect_prefers_modelines_and_shebangs() {
+        assert_eq!(FileType::detect("script", &["#!/usr/bin/env python3", "print(1)"]).name(), "Python");
+        assert_eq!(FileType::detect("notes.txt", &["", "// vim: ft=rust"]).name(), "Rust");
+        assert_eq!(FileType::detect("notes.txt", &["plain text"]).name(), "No filetype");
+    }
+}
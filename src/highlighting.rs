@@ -1,5 +1,4 @@
-use crossterm::style::Color;
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum Type {
     None,
     Number,
@@ -7,16 +6,38 @@ pub enum Type {
     String,
     Character,
     Comment,
+    PrimaryKeywords,
+    SecondaryKeywords,
+    Lifetime,
+    Macro,
+    Selection,
 }
+
+#[derive(PartialEq, Clone, Copy)]
+#[derive(Default)]
+pub enum State {
+    #[default]
+    Normal,
+    String(char),
+    TripleString(char),
+    RawString(usize),
+    BlockComment(usize),
+}
+
  impl Type {
-    pub fn to_color(&self) -> Color {
+    pub fn name(self) -> &'static str {
         match self {
-            Type::Number => Color::Rgb { r: 220, g: 163, b: 163 },
-            Type::Match => Color::Rgb { r: 38, g: 139, b: 210 },
-            Type::String => Color::Rgb { r: 211, g: 54, b: 130 },
-            Type::Character => Color::Rgb { r: 108, g: 113, b: 196 },
-            Type::Comment => Color::Rgb { r: 133, g: 153, b: 0 },
-            _ => Color::Rgb { r: 255, g: 255, b: 255 }
+            Type::None => "text",
+            Type::Number => "number",
+            Type::Match => "match",
+            Type::String => "string",
+            Type::Character => "character",
+            Type::Comment => "comment",
+            Type::PrimaryKeywords => "keyword",
+            Type::SecondaryKeywords => "type",
+            Type::Lifetime => "lifetime",
+            Type::Macro => "macro",
+            Type::Selection => "selection",
         }
     }
  }
\ No newline at end of file
@@ -0,0 +1,182 @@
+use crate::Position;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone)]
+pub enum Operation {
+    Insert { at: Position, text: String },
+    Delete { at: Position, text: String },
+}
+
+impl Operation {
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        match self {
+            Operation::Insert { at, text } => Operation::Delete { at: at.clone(), text: text.clone() },
+            Operation::Delete { at, text } => Operation::Insert { at: at.clone(), text: text.clone() },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Step {
+    id: usize,
+    pub operations: Vec<Operation>,
+    pub before: Position,
+    pub after: Position,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Step>,
+    redo_stack: Vec<Step>,
+    next_id: usize,
+    saved: usize,
+}
+
+impl History {
+    pub fn record(&mut self, mut operations: Vec<Operation>, before: &Position, after: &Position) {
+        self.redo_stack.clear();
+        if let Some(step) = self.undo_stack.last_mut().filter(|step| step.id != self.saved) {
+            if let (Some(last), Some(next)) = (step.operations.last(), operations.first()) {
+                if Self::continues(last, next) {
+                    step.operations.append(&mut operations);
+                    step.after = after.clone();
+                    return;
+                }
+            }
+        }
+        self.record_step(operations, before, after);
+    }
+
+    pub fn record_step(&mut self, operations: Vec<Operation>, before: &Position, after: &Position) {
+        self.redo_stack.clear();
+        self.next_id = self.next_id.saturating_add(1);
+        self.undo_stack.push(Step {
+            id: self.next_id,
+            operations,
+            before: before.clone(),
+            after: after.clone(),
+        });
+    }
+
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.undo_stack.pop()?;
+        self.redo_stack.push(step.clone());
+        Some(step)
+    }
+
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.redo_stack.pop()?;
+        self.undo_stack.push(step.clone());
+        Some(step)
+    }
+
+    #[must_use]
+    pub fn checkpoint(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn join(&mut self, from: usize) {
+        if self.undo_stack.len() <= from.saturating_add(1) {
+            return;
+        }
+        let steps: Vec<Step> = self.undo_stack.drain(from.saturating_add(1)..).collect();
+        if let Some(first) = self.undo_stack.last_mut() {
+            for mut step in steps {
+                first.operations.append(&mut step.operations);
+                first.after = step.after;
+                first.id = step.id;
+            }
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = self.current();
+    }
+
+    #[must_use]
+    pub fn is_saved(&self) -> bool {
+        self.current() == self.saved
+    }
+
+    fn current(&self) -> usize {
+        self.undo_stack.last().map_or(0, |step| step.id)
+    }
+
+    fn continues(last: &Operation, next: &Operation) -> bool {
+        match (last, next) {
+            (Operation::Insert { at, text }, Operation::Insert { at: next_at, text: next_text }) => {
+                text != "\n"
+                    && next_text != "\n"
+                    && at.y == next_at.y
+                    && at.x.saturating_add(text[..].graphemes(true).count()) == next_at.x
+            }
+            (Operation::Delete { at, text }, Operation::Delete { at: next_at, text: next_text }) => {
+                text != "\n"
+                    && next_text != "\n"
+                    && at.y == next_at.y
+                    && (next_at.x == at.x || next_at.x.saturating_add(1) == at.x)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(x: usize, text: &str) -> Vec<Operation> {
+        vec![Operation::Insert { at: Position { x, y: 0 }, text: text.to_string() }]
+    }
+
+    #[test]
+    fn undo_back_to_saved_state_is_clean() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), &Position::default(), &Position { x: 1, y: 0 });
+        history.mark_saved();
+        history.record(insert(1, "b"), &Position { x: 1, y: 0 }, &Position { x: 2, y: 0 });
+        assert!(!history.is_saved());
+        history.undo();
+        assert!(history.is_saved());
+        history.undo();
+        assert!(!history.is_saved());
+        history.redo();
+        assert!(history.is_saved());
+    }
+
+    #[test]
+    fn typing_after_save_starts_a_new_step() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), &Position::default(), &Position { x: 1, y: 0 });
+        history.mark_saved();
+        history.record(insert(1, "b"), &Position { x: 1, y: 0 }, &Position { x: 2, y: 0 });
+        let step = history.undo().unwrap();
+        assert_eq!(step.operations.len(), 1);
+        assert!(history.is_saved());
+    }
+
+    #[test]
+    fn joined_steps_undo_together() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), &Position::default(), &Position { x: 1, y: 0 });
+        let from = history.checkpoint();
+        history.record_step(vec![Operation::Delete { at: Position::default(), text: "a".to_string() }],
+            &Position::default(), &Position::default());
+        history.record(insert(0, "b"), &Position::default(), &Position { x: 1, y: 0 });
+        history.join(from);
+        assert_eq!(history.checkpoint(), 2);
+        let step = history.undo().unwrap();
+        assert_eq!(step.operations.len(), 2);
+        assert_eq!((step.after.x, step.before.x), (1, 0));
+    }
+
+    #[test]
+    fn consecutive_inserts_merge_into_one_step() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), &Position::default(), &Position { x: 1, y: 0 });
+        history.record(insert(1, "b"), &Position { x: 1, y: 0 }, &Position { x: 2, y: 0 });
+        assert_eq!(history.undo().unwrap().operations.len(), 2);
+        assert!(history.undo().is_none());
+    }
+}
@@ -0,0 +1,45 @@
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+mod editor;
+mod terminal;
+mod document;
+mod row;
+mod highlighting;
+mod filetype;
+mod history;
+mod rope;
+mod query;
+mod encoding;
+mod window;
+mod clipboard;
+mod syntax;
+mod theme;
+pub use terminal::Terminal;
+pub use terminal::ColorSupport;
+pub use editor::Position;
+pub use editor::SearchDirection;
+pub use document::Document;
+pub use document::LineEnding;
+pub use row::Row; 
+pub use row::Wrap;
+pub use filetype::FileType;
+pub use filetype::HighlightingOptions;
+pub use filetype::Indentation;
+pub use history::History;
+pub use history::Operation;
+pub use rope::Rope;
+pub use query::Query;
+pub use query::SearchOptions;
+pub use encoding::Encoding;
+pub use window::Layout;
+pub use window::Offset;
+pub use window::Rect;
+pub use window::Split;
+pub use window::Window;
+pub use clipboard::Clipboard;
+pub use syntax::SyntaxDefinition;
+pub use syntax::IndentSettings;
+pub use theme::Style;
+pub use theme::Theme;
+pub use editor::Editor;
+
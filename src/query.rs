@@ -0,0 +1,210 @@
+use crate::row::is_word;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Default, Clone, Copy)]
+pub struct SearchOptions {
+    regex: bool,
+    case_insensitive: bool,
+    whole_word: bool,
+}
+
+impl SearchOptions {
+    #[must_use]
+    pub fn regex(self) -> bool {
+        self.regex
+    }
+
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+    }
+
+    #[must_use]
+    pub fn case_insensitive(self) -> bool {
+        self.case_insensitive
+    }
+
+    pub fn toggle_case_insensitive(&mut self) {
+        self.case_insensitive = !self.case_insensitive;
+    }
+
+    #[must_use]
+    pub fn whole_word(self) -> bool {
+        self.whole_word
+    }
+
+    pub fn toggle_whole_word(&mut self) {
+        self.whole_word = !self.whole_word;
+    }
+
+    #[must_use]
+    pub fn describe(self) -> String {
+        let mut active = Vec::new();
+        if self.regex {
+            active.push("regex");
+        }
+        if self.case_insensitive {
+            active.push("ignore case");
+        }
+        if self.whole_word {
+            active.push("whole word");
+        }
+        if active.is_empty() {
+            return String::new();
+        }
+        format!("[{}]", active.join(", "))
+    }
+}
+
+pub struct Query {
+    regex: Regex,
+    options: SearchOptions,
+}
+
+impl Query {
+    pub fn new(text: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let pattern = if options.regex() {
+            text.to_string()
+        } else {
+            regex::escape(text)
+        };
+        Ok(Self {
+            regex: RegexBuilder::new(&pattern)
+                .case_insensitive(options.case_insensitive())
+                .build()?,
+            options,
+        })
+    }
+
+    #[must_use]
+    pub fn find(&self, haystack: &str, from: usize) -> Option<Range<usize>> {
+        let mut start = from;
+        while start <= haystack.len() {
+            let found = self.regex.find_at(haystack, start)?;
+            if self.accepts(haystack, &found.range()) {
+                return Some(found.range());
+            }
+            start = found.start() + haystack[found.start()..].chars().next()?.len_utf8();
+        }
+        None
+    }
+
+    #[must_use]
+    pub fn rfind(&self, haystack: &str, before: usize) -> Option<Range<usize>> {
+        self.matches(haystack).into_iter().rfind(|found| found.start < before)
+    }
+
+    #[must_use]
+    pub fn matches(&self, haystack: &str) -> Vec<Range<usize>> {
+        let mut matches: Vec<Range<usize>> = Vec::new();
+        let mut start = 0;
+        while let Some(found) = self.find(haystack, start) {
+            let follows_match = matches.last().is_some_and(|last| !last.is_empty() && last.end == found.start);
+            start = if found.is_empty() {
+                next_grapheme(haystack, found.start)
+            } else {
+                found.end
+            };
+            if !(found.is_empty() && follows_match) {
+                matches.push(found);
+            }
+        }
+        matches
+    }
+
+    #[must_use]
+    pub fn expand(&self, haystack: &str, at: Range<usize>, replacement: &str) -> Option<(Range<usize>, String)> {
+        let captures = self.regex.captures_at(haystack, at.start)?;
+        let found = captures.get(0)?;
+        let starts_in = found.start() == at.start || at.contains(&found.start());
+        if !starts_in || !self.accepts(haystack, &found.range()) {
+            return None;
+        }
+        let mut expanded = String::new();
+        if self.options.regex() {
+            captures.expand(replacement, &mut expanded);
+        } else {
+            expanded.push_str(replacement);
+        }
+        Some((found.range(), expanded))
+    }
+
+    fn accepts(&self, haystack: &str, range: &Range<usize>) -> bool {
+        if !self.options.whole_word() {
+            return true;
+        }
+        let before = haystack[..range.start].chars().next_back().is_some_and(is_word);
+        let after = haystack[range.end..].chars().next().is_some_and(is_word);
+        !before && !after
+    }
+}
+
+fn next_grapheme(haystack: &str, at: usize) -> usize {
+    haystack
+        .get(at..)
+        .and_then(|rest| rest.graphemes(true).next())
+        .map_or(haystack.len().saturating_add(1), |grapheme| at.saturating_add(grapheme.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str, regex: bool) -> Query {
+        let mut options = SearchOptions::default();
+        if regex {
+            options.toggle_regex();
+        }
+        Query::new(text, options).unwrap()
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        let query = query("a.b", false);
+        assert_eq!(query.matches("axb a.b"), vec![4..7]);
+    }
+
+    #[test]
+    fn case_insensitive_and_whole_word() {
+        let mut options = SearchOptions::default();
+        options.toggle_case_insensitive();
+        options.toggle_whole_word();
+        let query = Query::new("foo", options).unwrap();
+        assert_eq!(query.matches("Foo food _foo FOO"), vec![0..3, 14..17]);
+    }
+
+    #[test]
+    fn empty_matches_advance_by_grapheme() {
+        assert_eq!(query("^", true).matches("abc"), vec![0..0]);
+        assert_eq!(query("x*", true).matches("ae\u{301}"), vec![0..0, 1..1, 4..4]);
+    }
+
+    #[test]
+    fn empty_match_after_a_match_is_skipped() {
+        assert_eq!(query("a*", true).matches("bab"), vec![0..0, 1..2, 3..3]);
+    }
+
+    #[test]
+    fn rfind_returns_last_match_before() {
+        let query = query("ab", false);
+        assert_eq!(query.rfind("ab ab ab", 5), Some(3..5));
+        assert_eq!(query.rfind("ab ab ab", 0), None);
+    }
+
+    #[test]
+    fn expand_uses_capture_groups() {
+        let query = query(r"(\w+)@(\w+)", true);
+        let (range, expanded) = query.expand("mail bob@host", 5..6, "$2 at $1").unwrap();
+        assert_eq!(range, 5..13);
+        assert_eq!(expanded, "host at bob");
+        assert!(query.expand("mail bob@host", 0..1, "$1").is_none());
+    }
+
+    #[test]
+    fn expand_accepts_a_match_inside_the_grapheme() {
+        let query = query("\u{301}", false);
+        let (range, _) = query.expand("e\u{301}", 0..3, "").unwrap();
+        assert_eq!(range, 1..3);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::Row;
+use std::ops::{Index, IndexMut};
+
+const CHUNK_SIZE: usize = 512;
+
+#[derive(Default)]
+pub struct Rope {
+    chunks: Vec<Vec<Row>>,
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl Rope {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&Row> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get(offset)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Row> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get_mut(offset)
+    }
+
+    pub fn push(&mut self, row: Row) {
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => chunk.push(row),
+            _ => {
+                self.chunks.push(vec![row]);
+                self.starts.push(self.len);
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn insert(&mut self, index: usize, row: Row) {
+        if index >= self.len {
+            self.push(row);
+            return;
+        }
+        if let Some((chunk, offset)) = self.locate(index) {
+            self.chunks[chunk].insert(offset, row);
+            self.len += 1;
+            self.shift_starts(chunk, 1, 0);
+            if self.chunks[chunk].len() > CHUNK_SIZE * 2 {
+                self.split(chunk);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Row {
+        let (chunk, offset) = self.locate(index).expect("row index out of bounds");
+        let row = self.chunks[chunk].remove(offset);
+        self.len -= 1;
+        self.shift_starts(chunk, 0, 1);
+        if self.chunks[chunk].len() < CHUNK_SIZE / 2 {
+            self.merge(chunk);
+        }
+        row
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Row> {
+        self.chunks.iter().flatten()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Row> {
+        self.chunks.iter_mut().flatten()
+    }
+
+    fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        let chunk = self.starts.partition_point(|start| *start <= index).checked_sub(1)?;
+        Some((chunk, index - self.starts[chunk]))
+    }
+
+    fn shift_starts(&mut self, chunk: usize, added: usize, removed: usize) {
+        for start in &mut self.starts[chunk + 1..] {
+            *start = *start + added - removed;
+        }
+    }
+
+    fn split(&mut self, chunk: usize) {
+        let tail = self.chunks[chunk].split_off(CHUNK_SIZE);
+        self.chunks.insert(chunk + 1, tail);
+        self.starts.insert(chunk + 1, self.starts[chunk] + CHUNK_SIZE);
+    }
+
+    fn merge(&mut self, chunk: usize) {
+        let (left, right) = if chunk + 1 < self.chunks.len() {
+            (chunk, chunk + 1)
+        } else if chunk > 0 {
+            (chunk - 1, chunk)
+        } else {
+            if self.chunks[chunk].is_empty() {
+                self.chunks.clear();
+                self.starts.clear();
+            }
+            return;
+        };
+        let mut rows = self.chunks.remove(right);
+        self.starts.remove(right);
+        self.chunks[left].append(&mut rows);
+        if self.chunks[left].len() > CHUNK_SIZE * 2 {
+            self.split(left);
+        }
+    }
+}
+
+impl FromIterator<Row> for Rope {
+    fn from_iter<I: IntoIterator<Item = Row>>(iter: I) -> Self {
+        let mut rope = Self::default();
+        for row in iter {
+            rope.push(row);
+        }
+        rope
+    }
+}
+
+impl Index<usize> for Rope {
+    type Output = Row;
+
+    fn index(&self, index: usize) -> &Row {
+        self.get(index).expect("row index out of bounds")
+    }
+}
+
+impl IndexMut<usize> for Rope {
+    fn index_mut(&mut self, index: usize) -> &mut Row {
+        self.get_mut(index).expect("row index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rope(lines: usize) -> Rope {
+        (0..lines).map(|line| Row::from(line.to_string().as_str())).collect()
+    }
+
+    fn contents(rope: &Rope) -> Vec<String> {
+        rope.iter().map(|row| row.as_str().to_string()).collect()
+    }
+
+    #[test]
+    fn locates_rows_across_chunks() {
+        let rope = rope(CHUNK_SIZE * 3 + 7);
+        for index in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE * 2 + 3, CHUNK_SIZE * 3 + 6] {
+            assert_eq!(rope[index].as_str(), index.to_string());
+        }
+        assert!(rope.get(CHUNK_SIZE * 3 + 7).is_none());
+    }
+
+    #[test]
+    fn insert_splits_large_chunks() {
+        let mut rope = rope(CHUNK_SIZE);
+        let mut expected = contents(&rope);
+        for count in 0..CHUNK_SIZE * 2 {
+            rope.insert(10, Row::from(format!("new {count}").as_str()));
+            expected.insert(10, format!("new {count}"));
+        }
+        assert!(rope.chunks.len() > 1);
+        assert!(rope.chunks.iter().all(|chunk| chunk.len() <= CHUNK_SIZE * 2));
+        assert_eq!(contents(&rope), expected);
+        for (index, line) in expected.iter().enumerate() {
+            assert_eq!(rope[index].as_str(), line);
+        }
+    }
+
+    #[test]
+    fn remove_merges_small_chunks() {
+        let mut rope = rope(CHUNK_SIZE * 4);
+        let mut expected = contents(&rope);
+        for _ in 0..CHUNK_SIZE * 2 {
+            let index = CHUNK_SIZE + 5;
+            assert_eq!(rope.remove(index).as_str(), expected.remove(index));
+        }
+        assert_eq!(rope.len(), CHUNK_SIZE * 2);
+        assert!(rope.chunks.iter().take(rope.chunks.len() - 1).all(|chunk| chunk.len() >= CHUNK_SIZE / 2));
+        assert_eq!(contents(&rope), expected);
+        assert_eq!(rope[rope.len() - 1].as_str(), expected[expected.len() - 1]);
+    }
+
+    #[test]
+    fn removing_every_row_empties_the_rope() {
+        let mut rope = rope(CHUNK_SIZE + 1);
+        while !rope.is_empty() {
+            rope.remove(0);
+        }
+        assert!(rope.chunks.is_empty() && rope.starts.is_empty());
+        rope.insert(0, Row::from("again"));
+        assert_eq!(rope[0].as_str(), "again");
+    }
+}
@@ -1,17 +1,29 @@
-use std::cmp;
-use crossterm::style::SetForegroundColor;
+use crossterm::style::{Attribute, SetAttribute};
 use unicode_segmentation::UnicodeSegmentation;
-use crossterm::style::Color;
+use std::fmt::Write;
+use unicode_width::UnicodeWidthStr;
 
 use crate::SearchDirection;
 use crate::highlighting;
+use crate::highlighting::State;
 use crate::HighlightingOptions;
+use crate::Query;
+use crate::Theme;
 
 #[derive(Default)]
 pub struct Row {
     string: String,
     len: usize,
     highlighting: Vec<highlighting::Type>,
+    matches: Vec<usize>,
+    state: State,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Wrap {
+    Off,
+    Character,
+    Word,
 }
 
 impl From<&str> for Row {
@@ -20,43 +32,94 @@ impl From<&str> for Row {
             string: String::from(slice),
             len: slice.graphemes(true).count(),
             highlighting: Vec::new(),
+            matches: Vec::new(),
+            state: State::Normal,
         }
     }
 }
 
 impl Row {
-    pub fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start,end);
+    #[must_use]
+    pub fn render(&self, start: usize, end: usize, selection: Option<(usize, usize)>, theme: &Theme, tab_width: usize) -> String {
         let mut result = String::new();
-        let mut curr_highlighting = &highlighting::Type::None;
-        for (index, grapheme) in self.string[..] 
-        .graphemes(true)
-        .enumerate()
-        .skip(start)
-        .take(end - start)
-        {
-            if let Some(c) = grapheme.chars().next() {
-                let highlighting_type = self.highlighting.get(index).unwrap_or(&highlighting::Type::None);
-                if highlighting_type != curr_highlighting {
-                    curr_highlighting = highlighting_type;
-                    result.push_str(format!("{}", SetForegroundColor(highlighting_type.to_color())).as_str());
-                }
-                
-                if c == '\t' {
-                    result.push(' ')    
-                } else {
-                    result.push(c)
-                }
+        let mut current_style = None;
+        let mut column = 0;
+        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
+            if column >= end {
+                break;
+            }
+            let width = grapheme_width(grapheme, column, tab_width);
+            let next_column = column.saturating_add(width);
+            if next_column <= start {
+                column = next_column;
+                continue;
             }
+            let highlighting_type = self.highlighting.get(index).copied().unwrap_or(highlighting::Type::None);
+            let mut style = theme.highlight(highlighting_type);
+            if selection.is_some_and(|(from, to)| index >= from && index < to) {
+                style = style.overlay(theme.style(highlighting::Type::Selection.name()));
+            }
+            if current_style != Some(style) {
+                result.push_str(&style.escape());
+                current_style = Some(style);
+            }
+            if grapheme == "\t" || column < start || next_column > end {
+                let visible = next_column.min(end).saturating_sub(column.max(start));
+                result.push_str(&" ".repeat(visible));
+            } else {
+                result.push_str(grapheme);
+            }
+            column = next_column;
         }
-        result.push_str(format!("{}", SetForegroundColor(Color::Reset)).as_str());
+        write!(result, "{}", SetAttribute(Attribute::Reset)).ok();
         result
     }
+
+    pub fn wrap(&self, width: usize, tab_width: usize, words: bool) -> Vec<(usize, usize)> {
+        let width = width.max(1);
+        let mut lines = vec![(0, 0)];
+        let mut word_start = None;
+        let mut column = 0;
+        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
+            let grapheme_width = grapheme_width(grapheme, column, tab_width);
+            let (line_start, line_column) = lines[lines.len() - 1];
+            if column.saturating_add(grapheme_width).saturating_sub(line_column) > width && index > line_start {
+                let next_line = match word_start {
+                    Some((start, start_column)) if words && start > line_start => (start, start_column),
+                    _ => (index, column),
+                };
+                lines.push(next_line);
+                if column.saturating_add(grapheme_width).saturating_sub(next_line.1) > width && index > next_line.0 {
+                    lines.push((index, column));
+                }
+            }
+            column = column.saturating_add(grapheme_width);
+            if grapheme.chars().all(char::is_whitespace) {
+                word_start = Some((index.saturating_add(1), column));
+            }
+        }
+        lines
+    }
+
+    #[must_use]
+    pub fn display_width(&self, tab_width: usize) -> usize {
+        self.display_column(self.len, tab_width)
+    }
+
+    #[must_use]
+    pub fn display_column(&self, at: usize, tab_width: usize) -> usize {
+        self.string[..]
+            .graphemes(true)
+            .take(at)
+            .fold(0, |column, grapheme| column.saturating_add(grapheme_width(grapheme, column, tab_width)))
+    }
+
+    #[must_use]
     pub fn len(&self) -> usize {
         self.len
     }
     
+    #[must_use]
     pub fn is_empty(&self) -> bool{
         self.len == 0
     }
@@ -67,148 +130,245 @@ impl Row {
             self.len += 1;
             return;
         }
-        let mut result: String = String::new();
-        let mut length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            length += 1;
-            if index == at {
-                length += 1;
-                result.push(c);
-            }
-            result.push_str(grapheme);
-        }
-        self.len = length;
-        self.string = result;
-        
+        let byte_index = self.byte_index(at);
+        self.string.insert(byte_index, c);
+        self.len += 1;
     }
     
+    pub fn insert_str(&mut self, at: usize, text: &str) {
+        let byte_index = self.byte_index(at);
+        self.string.insert_str(byte_index, text);
+        self.len = self.string[..].graphemes(true).count();
+    }
+
+    #[must_use]
+    pub fn substring(&self, start: usize, end: usize) -> String {
+        self.string[..].graphemes(true).skip(start).take(end.saturating_sub(start)).collect()
+    }
+
+    #[must_use]
+    pub fn grapheme(&self, at: usize) -> Option<&str> {
+        self.string[..].graphemes(true).nth(at)
+    }
+
     pub fn delete(&mut self, at: usize) {
         if at >= self.len() {
             return;
         }
-        let mut result: String = String::new();
-        let mut length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            if index != at {
-                length += 1;
-                result.push_str(grapheme);
-            }
-        }
-        self.len = length;
-        self.string = result;
+        let start = self.byte_index(at);
+        let end = self.byte_index(at.saturating_add(1));
+        self.string.replace_range(start..end, "");
+        self.len -= 1;
     }
     
     pub fn append(&mut self, new: &Self) {
-        self.string = format!("{}{}", self.string, new.string);
+        self.string.push_str(&new.string);
         self.len += new.len; 
     }
     
+    #[must_use]
     pub fn split(&mut self, at: usize) -> Self {
-        let mut row: String = String::new();
-        let mut length = 0;
-        let mut splitted_row: String = String::new();
-        let mut splitted_length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            if index < at {
-                length += 1;
-                row.push_str(grapheme);
-            } else {
-                splitted_length += 1;
-                splitted_row.push_str(grapheme);
-            }
-        }
-        
-        self.string = row;
-        self.len = length;
+        let byte_index = self.byte_index(at);
+        let splitted_row = self.string.split_off(byte_index);
+        let splitted_length = self.len.saturating_sub(at);
+        self.len = self.len.saturating_sub(splitted_length);
         Self {
             string: splitted_row,
             len: splitted_length,
             highlighting: Vec::new(),
+            matches: Vec::new(),
+            state: State::Normal,
         }
     }
     
+    fn grapheme_index(&self, byte_index: usize) -> usize {
+        self.string[..]
+            .grapheme_indices(true)
+            .take_while(|(index, _)| *index < byte_index)
+            .count()
+    }
+    
+    fn grapheme_containing(&self, byte_index: usize) -> usize {
+        self.string[..]
+            .grapheme_indices(true)
+            .position(|(index, grapheme)| byte_index < index.saturating_add(grapheme.len()))
+            .unwrap_or(self.len)
+    }
+    
+    fn byte_index(&self, at: usize) -> usize {
+        self.string[..]
+            .grapheme_indices(true)
+            .nth(at)
+            .map_or(self.string.len(), |(byte_index, _)| byte_index)
+    }
+    
+    #[must_use]
     pub fn as_bytes(&self) -> &[u8] {
         self.string.as_bytes()
     }
     
-    pub fn find(&self, query: &str, at: usize, direction: SearchDirection) -> Option<usize> {
-        if at > self.len || query.is_empty() {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+    
+    #[must_use]
+    pub fn find(&self, query: &Query, at: usize, direction: SearchDirection) -> Option<usize> {
+        if at > self.len {
             return None;
         }
-        let start = if direction == SearchDirection::Forward {
-            at
+        let byte_index = self.byte_index(at);
+        let found = if direction == SearchDirection::Forward {
+            query.find(&self.string, byte_index)
         } else {
-            0
+            query.rfind(&self.string, byte_index)
         };
-        let end  = if direction == SearchDirection::Forward {
-            self.len
-        } else {
-            at
-        };
-        let substring: String = self.string[..].graphemes(true).skip(start).take(end - start).collect();
-        let matching_byte_index = if direction == SearchDirection::Forward {
-            substring.find(query)
-        } else {
-            substring.rfind(query)
-        };
-        if let Some(matching_byte_index) = matching_byte_index {
-            for (grapheme_index, (byte_index,_)) in 
-            substring[..].grapheme_indices(true).enumerate() {
-                if matching_byte_index == byte_index {
-                    return Some(start + grapheme_index)
-                }
-            } 
-        }
-        None
+        found.map(|range| self.grapheme_containing(range.start))
     }
     
-    pub fn highlight(&mut self,options: HighlightingOptions, word: Option<&str>) {
-        let mut highlighting = Vec::new();
-        let chars: Vec<char> = self.string.chars().collect();
-        let mut matches = Vec::new();
-        let mut search_index = 0;
-        if let Some(word) = word {
-            while let Some(search_match) = self.find(word, search_index, SearchDirection::Forward) {
-                matches.push(search_match);
-                if let Some(next_index) = search_match.checked_add(word[..].graphemes(true).count()) {
-                    search_index = next_index;
-                } else {
-                    break;
+    #[must_use]
+    pub fn replacement(&self, query: &Query, at: usize, replacement: &str) -> Option<(String, String)> {
+        let start = self.byte_index(at);
+        let next = self.byte_index(at.saturating_add(1));
+        let (range, expanded) = query.expand(&self.string, start..next, replacement)?;
+        let end = self.byte_index(self.grapheme_index(range.end));
+        let expanded = format!("{}{}{}", &self.string[start..range.start], expanded, &self.string[range.end..end]);
+        Some((self.string[start..end].to_string(), expanded))
+    }
+    
+    #[must_use]
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+    
+    pub fn highlight_match(&mut self, query: &Query, at: usize) {
+        if let Some(range) = query.find(&self.string, self.byte_index(at)) {
+            if self.grapheme_containing(range.start) != at {
+                return;
+            }
+            for index in at..self.grapheme_index(range.end) {
+                if let Some(highlighting) = self.highlighting.get_mut(index) {
+                    *highlighting = highlighting::Type::Match;
                 }
             }
         }
+    }
+    
+    #[allow(clippy::too_many_lines)]
+    pub fn highlight(&mut self, options: &HighlightingOptions, word: Option<&Query>, start: State) {
+        let mut highlighting = Vec::new();
+        let chars: Vec<char> = self.string[..]
+            .graphemes(true)
+            .map(|grapheme| grapheme.chars().next().unwrap_or(' '))
+            .collect();
+        let matches: Vec<(usize, usize)> = word.map_or_else(Vec::new, |word| {
+            word.matches(&self.string)
+                .into_iter()
+                .map(|range| (self.grapheme_containing(range.start), self.grapheme_index(range.end)))
+                .collect()
+        });
+        self.matches = matches.iter().map(|(start, _)| *start).collect();
         
+        let mut state = start;
         let mut prev_is_separator = true;
-        let mut in_string = false;
         let mut index = 0;
         while let Some(c) = chars.get(index) {
-            if let Some(word) = word {
-                if matches.contains(&index) {
-                    for _ in word[..].graphemes(true) {
-                        index += 1;
-                        highlighting.push(highlighting::Type::Match);
+            let next_char = chars.get(index.saturating_add(1));
+            match state {
+                State::BlockComment(depth) => {
+                    let (open, close) = options.block_comment();
+                    let (len, next_state) = if starts_with(&chars[index..], close) {
+                        (close.chars().count(), if depth > 1 { State::BlockComment(depth - 1) } else { State::Normal })
+                    } else if options.nested_comments() && starts_with(&chars[index..], open) {
+                        (open.chars().count(), State::BlockComment(depth.saturating_add(1)))
+                    } else {
+                        (1, state)
+                    };
+                    for _ in 0..len {
+                        highlighting.push(highlighting::Type::Comment);
                     }
+                    index += len;
+                    state = next_state;
+                    prev_is_separator = true;
                     continue;
                 }
-            }
-            let previous_highlight = if index > 0{
-                highlighting.get(index-1).unwrap_or(&highlighting::Type::None)
-            } else {
-                &highlighting::Type::None
-            };
-            if options.comments() && *c == '/' {
-                if let Some(next_char) = chars.get(index.saturating_add(1)) {
-                    if *next_char = '/' {
-                        for _ in index..chars.len() {
-                            highlighting.push(highlighting::Type::Comment);
+                State::RawString(hashes) => {
+                    highlighting.push(highlighting::Type::String);
+                    index += 1;
+                    let closes = chars
+                        .get(index..index.saturating_add(hashes))
+                        .is_some_and(|tail| tail.iter().all(|c| *c == '#'));
+                    if *c == '"' && closes {
+                        for _ in 0..hashes {
+                            highlighting.push(highlighting::Type::String);
                         }
-                        break;
+                        index += hashes;
+                        state = State::Normal;
+                        prev_is_separator = true;
                     }
-                };
+                    continue;
+                }
+                State::TripleString(delimiter) => {
+                    let len = if starts_with(&chars[index..], &delimiter.to_string().repeat(3)) {
+                        state = State::Normal;
+                        prev_is_separator = true;
+                        3
+                    } else if *c == '\\' && next_char.is_some() {
+                        2
+                    } else {
+                        1
+                    };
+                    for _ in 0..len {
+                        highlighting.push(highlighting::Type::String);
+                    }
+                    index += len;
+                    continue;
+                }
+                State::String(delimiter) => {
+                    highlighting.push(highlighting::Type::String);
+                    let escapes = !options.raw_delimiters().contains(&delimiter);
+                    if escapes && *c == '\\' && next_char.is_some() {
+                        highlighting.push(highlighting::Type::String);
+                        index += 2;
+                        continue;
+                    }
+                    if *c == delimiter {
+                        state = State::Normal;
+                        prev_is_separator = true;
+                    }
+                    index += 1;
+                    continue;
+                }
+                State::Normal => (),
             }
-            if options.characters() {
-                prev_is_separator = true;
-                if let Some(next_char) = chars.get(index.saturating_add(1)) {
+            let (open, _) = options.block_comment();
+            if options.multiline_comments() && starts_with(&chars[index..], open) {
+                for _ in open.chars() {
+                    highlighting.push(highlighting::Type::Comment);
+                }
+                index += open.chars().count();
+                state = State::BlockComment(1);
+                continue;
+            }
+            if options.comments() && starts_with(&chars[index..], options.line_comment()) {
+                for _ in index..chars.len() {
+                    highlighting.push(highlighting::Type::Comment);
+                }
+                break;
+            }
+            if options.raw_strings() && prev_is_separator && *c == 'r' {
+                let hashes = chars[index.saturating_add(1)..].iter().take_while(|c| **c == '#').count();
+                if chars.get(index.saturating_add(hashes).saturating_add(1)) == Some(&'"') {
+                    for _ in 0..hashes.saturating_add(2) {
+                        highlighting.push(highlighting::Type::String);
+                    }
+                    index += hashes.saturating_add(2);
+                    state = State::RawString(hashes);
+                    continue;
+                }
+            }
+            if options.characters() && *c == '\'' {
+                if let Some(next_char) = next_char {
                     let closing_index = if *next_char =='\\' {
                         index.saturating_add(3)
                     } else {
@@ -220,51 +380,336 @@ impl Row {
                                 highlighting.push(highlighting::Type::Character);
                                 index += 1;
                             }
+                            prev_is_separator = true;
                             continue;
                         }
                     }
-                };           
-                highlighting.push(highlighting::Type::None);
+                }           
+            }
+            if options.lifetimes() && *c == '\'' && next_char.is_some_and(|c| is_word(*c)) {
+                highlighting.push(highlighting::Type::Lifetime);
                 index += 1;
+                while chars.get(index).is_some_and(|c| is_word(*c)) {
+                    highlighting.push(highlighting::Type::Lifetime);
+                    index += 1;
+                }
+                prev_is_separator = true;
                 continue;
             }
-            if options.strings() {
-                if in_string {
-                    highlighting.push(highlighting::Type::String);
-                    if *c == '\\' && index < self.len().saturating_sub(1) {
-                        highlighting.push(highlighting::Type::String);
-                        index += 2;
-                        continue;
+            if is_word(*c) && !c.is_ascii_digit() && (index == 0 || !is_word(chars[index - 1])) {
+                if let Some((highlighting_type, len)) = Self::classify_word(options, &chars[index..]) {
+                    for _ in 0..len {
+                        highlighting.push(highlighting_type);
                     }
-                    if *c == '"' {
-                        in_string = false;
-                        prev_is_separator = true;
-                    } else {
-                        prev_is_separator = false;
-                    }
-                    index += 1;
-                    continue;
-                } else if prev_is_separator && *c == '"' {
-                    highlighting.push(highlighting::Type::String);
-                    in_string = true;
+                    index += len;
                     prev_is_separator = true;
-                    index += 1;
                     continue;
                 }
             }
-            if options.numbers() {
-                if (c.is_ascii_digit() && (prev_is_separator || *previous_highlight == highlighting::Type::Number))
-                || (*c == '.' && *previous_highlight == highlighting::Type::Number) {
+            if options.strings() && prev_is_separator && options.string_delimiters().contains(c) {
+                if options.triple_quotes() && starts_with(&chars[index..], &c.to_string().repeat(3)) {
+                    for _ in 0..3 {
+                        highlighting.push(highlighting::Type::String);
+                    }
+                    state = State::TripleString(*c);
+                    index += 3;
+                    continue;
+                }
+                highlighting.push(highlighting::Type::String);
+                state = State::String(*c);
+                index += 1;
+                continue;
+            }
+            if options.numbers() && prev_is_separator && c.is_ascii_digit() {
+                let len = Self::number_length(options, &chars[index..]);
+                for _ in 0..len {
                     highlighting.push(highlighting::Type::Number);
-                } else {
-                    highlighting.push(highlighting::Type::None);
                 }
-            } else {
-                highlighting.push(highlighting::Type::None);
+                index += len;
+                prev_is_separator = false;
+                continue;
             }
+            highlighting.push(highlighting::Type::None);
             prev_is_separator = c.is_ascii_punctuation() || c.is_ascii_whitespace();    
             index += 1;
         }
+        if let State::String(delimiter) = state {
+            if !options.multiline_strings().contains(&delimiter) {
+                state = State::Normal;
+            }
+        }
+        for (start, end) in matches {
+            for highlighting in highlighting.iter_mut().take(end).skip(start) {
+                *highlighting = highlighting::Type::Match;
+            }
+        }
         self.highlighting = highlighting;
+        self.state = state;
+    }
+
+    #[must_use]
+    pub fn highlight_state(&self) -> State {
+        self.state
+    }
+
+    fn number_length(options: &HighlightingOptions, chars: &[char]) -> usize {
+        let separator = options.number_separator();
+        let prefix = options.number_prefixes().iter().find(|prefix| starts_with_ignore_case(chars, prefix));
+        if let Some(prefix) = prefix {
+            let radix = match prefix.chars().last().map(|c| c.to_ascii_lowercase()) {
+                Some('x') => 16,
+                Some('o') => 8,
+                Some('b') => 2,
+                _ => 10,
+            };
+            let prefix_len = prefix.chars().count();
+            let digits = digits_length(&chars[prefix_len..], radix, separator);
+            if digits > 0 {
+                let len = prefix_len + digits;
+                return len + suffix_length(options, &chars[len..]);
+            }
+        }
+        let mut len = digits_length(chars, 10, separator);
+        if chars.get(len) == Some(&'.') && chars.get(len + 1).is_some_and(char::is_ascii_digit) {
+            len += 1 + digits_length(&chars[len + 1..], 10, separator);
+        }
+        if matches!(chars.get(len), Some('e' | 'E')) {
+            let sign = usize::from(matches!(chars.get(len + 1), Some('+' | '-')));
+            let digits = chars.get(len + 1 + sign..).map_or(0, |rest| digits_length(rest, 10, None));
+            if digits > 0 {
+                len += 1 + sign + digits;
+            }
+        }
+        len + suffix_length(options, &chars[len..])
     }
-}
\ No newline at end of file
+
+    fn classify_word(options: &HighlightingOptions, chars: &[char]) -> Option<(highlighting::Type, usize)> {
+        let len = chars.iter().take_while(|c| is_word(**c)).count();
+        let word: String = chars[..len].iter().collect();
+        if options.macros() && chars.get(len) == Some(&'!') && chars.get(len.saturating_add(1)) != Some(&'=') {
+            return Some((highlighting::Type::Macro, len.saturating_add(1)));
+        }
+        if options.primary_keywords().contains(&word) {
+            return Some((highlighting::Type::PrimaryKeywords, len));
+        }
+        if options.secondary_keywords().contains(&word) {
+            return Some((highlighting::Type::SecondaryKeywords, len));
+        }
+        None
+    }
+}
+
+pub fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn starts_with(chars: &[char], prefix: &str) -> bool {
+    !prefix.is_empty() && prefix.chars().enumerate().all(|(offset, c)| chars.get(offset) == Some(&c))
+}
+
+fn starts_with_ignore_case(chars: &[char], prefix: &str) -> bool {
+    !prefix.is_empty()
+        && prefix.chars().enumerate().all(|(offset, c)| chars.get(offset).is_some_and(|d| d.eq_ignore_ascii_case(&c)))
+}
+
+fn digits_length(chars: &[char], radix: u32, separator: Option<char>) -> usize {
+    let mut len = 0;
+    while let Some(c) = chars.get(len) {
+        let separates = Some(*c) == separator && len > 0 && chars.get(len + 1).is_some_and(|next| next.is_digit(radix));
+        if !c.is_digit(radix) && !separates {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
+
+fn suffix_length(options: &HighlightingOptions, chars: &[char]) -> usize {
+    let separated = options.number_separator().is_some_and(|separator| chars.first() == Some(&separator));
+    let skipped = usize::from(separated);
+    let chars = &chars[skipped..];
+    let len = options
+        .number_suffixes()
+        .iter()
+        .map(|suffix| (suffix, suffix.chars().count()))
+        .filter(|(suffix, len)| starts_with_ignore_case(chars, suffix) && !chars.get(*len).is_some_and(|c| is_word(*c)))
+        .map(|(_, len)| len)
+        .max()
+        .unwrap_or(0);
+    if len > 0 {
+        skipped + len
+    } else {
+        0
+    }
+}
+
+fn grapheme_width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - column % tab_width
+    } else {
+        grapheme.width()
+    }
+}
+
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.graphemes(true)
+        .take_while(|grapheme| {
+            used += grapheme.width();
+            used <= width
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileType, SearchOptions};
+
+    fn highlighted(text: &str, word: Option<&str>, start: State) -> Row {
+        let file_type = FileType::from("main.rs");
+        let query = word.map(|word| Query::new(word, SearchOptions::default()).unwrap());
+        let mut row = Row::from(text);
+        row.highlight(file_type.highlighting_options(), query.as_ref(), start);
+        row
+    }
+
+    fn plain(rendered: &str) -> String {
+        let mut text = String::new();
+        let mut escape = false;
+        for c in rendered.chars() {
+            match c {
+                '\x1b' => escape = true,
+                'm' if escape => escape = false,
+                _ if escape => (),
+                _ => text.push(c),
+            }
+        }
+        text
+    }
+
+    fn rendered(text: &str, start: usize, end: usize) -> String {
+        let theme = Theme::built_in().remove(0);
+        plain(&Row::from(text).render(start, end, None, &theme, 4))
+    }
+
+    #[test]
+    fn display_column_counts_wide_graphemes_and_tabs() {
+        let row = Row::from("a\t\u{4e2d}\u{1f600}b");
+        let columns: Vec<usize> = (0..=5).map(|at| row.display_column(at, 4)).collect();
+        assert_eq!(columns, vec![0, 1, 4, 6, 8, 9]);
+        assert_eq!(row.display_width(4), 9);
+        assert_eq!(Row::from("ab\tc").display_column(3, 8), 8);
+    }
+
+    #[test]
+    fn render_pads_wide_graphemes_cut_by_the_view() {
+        assert_eq!(rendered("\u{4e2d}\u{6587}", 0, 4), "\u{4e2d}\u{6587}");
+        assert_eq!(rendered("\u{4e2d}\u{6587}", 1, 4), " \u{6587}");
+        assert_eq!(rendered("\u{4e2d}\u{6587}", 0, 3), "\u{4e2d} ");
+        assert_eq!(rendered("\u{1f600}x", 0, 1), " ");
+        assert_eq!(rendered("\u{1f600}x", 2, 3), "x");
+    }
+
+    #[test]
+    fn render_expands_tabs_to_the_next_stop() {
+        assert_eq!(rendered("a\tb", 0, 10), "a   b");
+        assert_eq!(rendered("a\tb", 2, 10), "  b");
+        assert_eq!(rendered("\tb", 0, 2), "  ");
+    }
+
+    #[test]
+    fn truncate_to_width_keeps_whole_graphemes() {
+        assert_eq!(truncate_to_width("\u{4e2d}\u{6587}\u{5b57}", 5), "\u{4e2d}\u{6587}");
+        assert_eq!(truncate_to_width("\u{1f600}a", 1), "");
+        assert_eq!(truncate_to_width("\u{1f600}a", 3), "\u{1f600}a");
+        assert_eq!(truncate_to_width("e\u{301}x", 1), "e\u{301}");
+        assert_eq!(truncate_to_width("abc", 0), "");
+    }
+
+    #[test]
+    fn wrap_splits_by_display_width() {
+        assert_eq!(Row::from("abcdef").wrap(3, 4, false), vec![(0, 0), (3, 3)]);
+        assert_eq!(Row::from("abc").wrap(3, 4, false), vec![(0, 0)]);
+        assert_eq!(Row::from("a\u{4e2d}\u{6587}").wrap(4, 4, false), vec![(0, 0), (2, 3)]);
+        assert_eq!(Row::from("ab cd ef").wrap(4, 4, true), vec![(0, 0), (3, 3), (6, 6)]);
+    }
+
+    #[test]
+    fn search_matches_do_not_change_the_end_state() {
+        assert!(highlighted("/* a */ b", Some("*/"), State::Normal).highlight_state() == State::Normal);
+        assert!(highlighted("a \" b", Some("\""), State::Normal).highlight_state() == State::String('"'));
+        let row = highlighted("x */ y", Some("*/"), State::BlockComment(1));
+        assert!(row.highlight_state() == State::Normal);
+        assert!(row.highlighting[2] == highlighting::Type::Match);
+        assert!(row.highlighting[5] == highlighting::Type::None);
+    }
+
+    #[test]
+    fn only_multiline_strings_continue_on_the_next_row() {
+        let end_state = |file_name: &str, text: &str| {
+            let mut row = Row::from(text);
+            row.highlight(FileType::from(file_name).highlighting_options(), None, State::Normal);
+            row.highlight_state()
+        };
+        assert!(end_state("main.rs", "let s = \"open") == State::String('"'));
+        assert!(end_state("main.c", "char *s = \"open") == State::Normal);
+        assert!(end_state("main.go", "s := \"open") == State::Normal);
+        assert!(end_state("main.go", "s := `open") == State::String('`'));
+        assert!(end_state("data.json", "{\"open") == State::Normal);
+        assert!(end_state("script.py", "s = 'open") == State::Normal);
+        assert!(end_state("run.sh", "echo \"open") == State::String('"'));
+    }
+
+    #[test]
+    fn python_triple_quoted_strings_span_rows() {
+        let file_type = FileType::from("script.py");
+        let mut row = Row::from("x = \"\"\"doc \" still");
+        row.highlight(file_type.highlighting_options(), None, State::Normal);
+        assert!(row.highlight_state() == State::TripleString('"'));
+        let mut row = Row::from("end \\\"\"\"\" y = 1");
+        row.highlight(file_type.highlighting_options(), None, State::TripleString('"'));
+        assert!(row.highlight_state() == State::Normal);
+        assert!(row.highlighting[7] == highlighting::Type::String);
+        assert!(row.highlighting[9] == highlighting::Type::None);
+        let mut row = Row::from("''");
+        row.highlight(file_type.highlighting_options(), None, State::Normal);
+        assert!(row.highlight_state() == State::Normal);
+    }
+
+    #[test]
+    fn go_raw_strings_ignore_backslashes() {
+        let file_type = FileType::from("main.go");
+        let mut row = Row::from("p := `C:\\` + x");
+        row.highlight(file_type.highlighting_options(), None, State::Normal);
+        assert!(row.highlight_state() == State::Normal);
+        assert!(row.highlighting[11] == highlighting::Type::None);
+        let mut row = Row::from("s := \"a\\\" b\"");
+        row.highlight(file_type.highlighting_options(), None, State::Normal);
+        assert!(row.highlight_state() == State::Normal);
+    }
+
+    fn numbers(file_name: &str, text: &str) -> String {
+        let file_type = FileType::from(file_name);
+        let mut row = Row::from(text);
+        row.highlight(file_type.highlighting_options(), None, State::Normal);
+        row.highlighting.iter().map(|kind| if *kind == highlighting::Type::Number { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn number_rules_cover_prefixes_separators_and_suffixes() {
+        assert_eq!(numbers("main.rs", "0xFF_u8 1_000 2.5e-3f64 x1 1.max"), "####### ##### ######### .. #....".replace(' ', "."));
+        assert_eq!(numbers("main.c", "0x1fUL 1'000 'a'"), "###### ##### ...".replace(' ', "."));
+        assert_eq!(numbers("app.js", "10n 0b101 12px"), "### ##### ##..".replace(' ', "."));
+    }
+
+    #[test]
+    fn block_comments_carry_over_rows() {
+        let row = highlighted("let a = 1; /* open", None, State::Normal);
+        assert!(row.highlight_state() == State::BlockComment(1));
+        let row = highlighted("/* nested */ still", None, State::BlockComment(1));
+        assert!(row.highlight_state() == State::BlockComment(1));
+        assert!(row.highlighting.iter().all(|kind| *kind == highlighting::Type::Comment));
+    }
+}
@@ -0,0 +1,206 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static DEFINITIONS: OnceLock<Vec<SyntaxDefinition>> = OnceLock::new();
+static INDENT_SETTINGS: OnceLock<HashMap<String, IndentSettings>> = OnceLock::new();
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SyntaxDefinition {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub file_names: Vec<String>,
+    pub interpreters: Vec<String>,
+    pub numbers: bool,
+    pub number_prefixes: Vec<String>,
+    pub number_separator: Option<char>,
+    pub number_suffixes: Vec<String>,
+    pub characters: bool,
+    pub string_delimiters: Vec<char>,
+    pub multiline_strings: Vec<char>,
+    pub line_comment: Option<String>,
+    pub block_comment: Option<(String, String)>,
+    pub nested_comments: bool,
+    pub primary_keywords: Vec<String>,
+    pub secondary_keywords: Vec<String>,
+    pub indent_width: Option<usize>,
+    pub indent_tabs: bool,
+    pub indent_after: Vec<char>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndentSettings {
+    pub width: Option<usize>,
+    pub tabs: Option<bool>,
+    pub after: Option<Vec<char>>,
+}
+
+impl SyntaxDefinition {
+    pub fn all() -> &'static [Self] {
+        DEFINITIONS.get().map_or(&[], Vec::as_slice)
+    }
+
+    pub fn load_all() -> Vec<String> {
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+        let indent_path = config_dir().map(|dir| dir.join("indent.toml")).filter(|path| path.is_file());
+        let indent_settings = indent_path.map_or_else(|| Ok(HashMap::new()), |path| IndentSettings::load(&path));
+        INDENT_SETTINGS.set(indent_settings.unwrap_or_else(|error| {
+            errors.push(error);
+            HashMap::new()
+        })).ok();
+        if let Some(dir) = config_dir().map(|dir| dir.join("syntax")) {
+            let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+                .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+                .unwrap_or_default();
+            paths.retain(|path| path.extension().is_some_and(|extension| extension == "toml"));
+            paths.sort();
+            for path in paths {
+                match Self::load(&path) {
+                    Ok(definition) => definitions.push(definition),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        DEFINITIONS.set(definitions).ok();
+        errors
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let definition: Self = parse(path)?;
+        definition.validate().map_err(|reason| format!("{}: {}", path.display(), reason))?;
+        Ok(definition)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("missing `name`".to_string());
+        }
+        if self.extensions.is_empty() && self.file_names.is_empty() && self.interpreters.is_empty() {
+            return Err("needs at least one of `extensions`, `file_names` or `interpreters`".to_string());
+        }
+        if self.line_comment.as_ref().is_some_and(String::is_empty) {
+            return Err("`line_comment` must not be empty".to_string());
+        }
+        if let Some((open, close)) = &self.block_comment {
+            if open.is_empty() || close.is_empty() {
+                return Err("`block_comment` needs a non-empty start and end token".to_string());
+            }
+        }
+        if let Some(delimiter) = self.string_delimiters.iter().find(|c| c.is_alphanumeric() || c.is_whitespace()) {
+            return Err(format!("'{delimiter}' cannot be used as a string delimiter"));
+        }
+        if let Some(delimiter) = self.multiline_strings.iter().find(|c| !self.string_delimiters.contains(c)) {
+            return Err(format!("multi-line string '{delimiter}' is not one of the string delimiters"));
+        }
+        if let Some(prefix) = self.number_prefixes.iter().find(|prefix| !prefix.starts_with(|c: char| c.is_ascii_digit())) {
+            return Err(format!("number prefix \"{prefix}\" must start with a digit"));
+        }
+        if let Some(separator) = self.number_separator.filter(|c| c.is_alphanumeric() || c.is_whitespace()) {
+            return Err(format!("'{separator}' cannot be used as a number separator"));
+        }
+        if self.indent_width == Some(0) {
+            return Err("`indent_width` must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn matches_file_name(&self, file_name: &str) -> bool {
+        self.file_names.iter().any(|name| name == file_name)
+    }
+
+    #[must_use]
+    pub fn matches_extension(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|candidate| candidate.trim_start_matches('.') == extension)
+    }
+
+    #[must_use]
+    pub fn matches_language(&self, language: &str) -> bool {
+        self.name.eq_ignore_ascii_case(language) || self.interpreters.iter().any(|name| name == language)
+    }
+}
+
+impl IndentSettings {
+    pub fn find(file_type: &str) -> Option<&'static Self> {
+        INDENT_SETTINGS
+            .get()?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(file_type))
+            .map(|(_, settings)| settings)
+    }
+
+    fn load(path: &Path) -> Result<HashMap<String, Self>, String> {
+        let settings: HashMap<String, Self> = parse(path)?;
+        if let Some(name) = settings.iter().find_map(|(name, settings)| (settings.width == Some(0)).then_some(name)) {
+            return Err(format!("{}: [{}] `width` must be at least 1", path.display(), name));
+        }
+        Ok(settings)
+    }
+}
+
+fn parse<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let source = path.display();
+    let text = fs::read_to_string(path).map_err(|error| format!("{source}: {error}"))?;
+    toml::from_str(&text).map_err(|error| {
+        let line = error.span().map_or(1, |span| text[..span.start].matches('\n').count().saturating_add(1));
+        format!("{}:{}: {}", source, line, error.message())
+    })
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, text: &str) -> Result<SyntaxDefinition, String> {
+        let path = env::temp_dir().join(format!("syntax-test-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let definition = SyntaxDefinition::load(&path);
+        fs::remove_file(&path).ok();
+        definition
+    }
+
+    #[test]
+    fn loads_number_rules() {
+        let definition = load(
+            "numbers",
+            "name = \"Zig\"\nextensions = [\"zig\"]\nnumbers = true\nnumber_prefixes = [\"0x\"]\nnumber_separator = \"_\"\nnumber_suffixes = []\n",
+        )
+        .unwrap();
+        assert_eq!(definition.number_prefixes, vec!["0x".to_string()]);
+        assert_eq!(definition.number_separator, Some('_'));
+    }
+
+    #[test]
+    fn rejects_invalid_number_rules() {
+        let error = load("prefix", "name = \"X\"\nextensions = [\"x\"]\nnumber_prefixes = [\"x\"]\n").err().unwrap();
+        assert!(error.contains("must start with a digit"));
+        let error = load("separator", "name = \"X\"\nextensions = [\"x\"]\nnumber_separator = \"a\"\n").err().unwrap();
+        assert!(error.contains("number separator"));
+        let error = load("unknown", "name = \"X\"\nextensions = [\"x\"]\nnumber = true\n").err().unwrap();
+        assert!(error.contains(":3:"));
+    }
+
+    #[test]
+    fn multiline_strings_must_be_string_delimiters() {
+        let definition = load("multiline", "name = \"X\"\nextensions = [\"x\"]\nstring_delimiters = [\"`\"]\nmultiline_strings = [\"`\"]\n").unwrap();
+        assert_eq!(definition.multiline_strings, vec!['`']);
+        let error = load("stray", "name = \"X\"\nextensions = [\"x\"]\nmultiline_strings = [\"`\"]\n").err().unwrap();
+        assert!(error.contains("multi-line string"));
+    }
+}
@@ -1,23 +1,125 @@
 use std::io::{stdout, Write};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::env;
 use crossterm::{
     cursor,
     terminal::{ClearType, self},
     execute,
-    style::{Colors, SetColors, ResetColor}, queue,
+    style::{Attribute, Color, SetAttribute}, queue,
 };
 
 use crate::editor::Position;
+use crate::Style;
 pub struct Size {
     pub width: u16,
     pub height: u16,
 }
 pub struct Terminal {
     pub size: Size,
+    color_support: ColorSupport,
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    #[must_use]
+    pub fn detect() -> Self {
+        if let Some(support) = env::var("EDITOR_COLORS").ok().and_then(|value| Self::from_name(&value)) {
+            return support;
+        }
+        let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default().to_lowercase();
+        if term.ends_with("-direct") || term.contains("truecolor") || term.contains("24bit") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "truecolor" | "24bit" | "rgb" => Some(ColorSupport::TrueColor),
+            "256" | "ansi256" => Some(ColorSupport::Ansi256),
+            "16" | "ansi16" | "ansi" => Some(ColorSupport::Ansi16),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn adapt(self, color: Color) -> Color {
+        let Color::Rgb { r, g, b } = color else {
+            return color;
+        };
+        match self {
+            ColorSupport::TrueColor => color,
+            ColorSupport::Ansi256 => Color::AnsiValue(nearest_ansi_value((r, g, b))),
+            ColorSupport::Ansi16 => ANSI_COLORS
+                .iter()
+                .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+                .map_or(color, |(ansi, _)| *ansi),
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+fn nearest_ansi_value(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| {
+        (0..6u8)
+            .min_by_key(|index| CUBE_LEVELS[usize::from(*index)].abs_diff(value))
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE_LEVELS[usize::from(r)], CUBE_LEVELS[usize::from(g)], CUBE_LEVELS[usize::from(b)]);
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + gray_index * 10;
+    if distance((gray_level, gray_level, gray_level), rgb) < distance(cube, rgb) {
+        232 + gray_index
+    } else {
+        16 + r * 36 + g * 6 + b
+    }
 }
 
 
 impl Terminal {
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Terminal, std::io::Error>{
         let size = terminal::size().unwrap();
         terminal::enable_raw_mode().expect("Could not turn on Raw mode");
@@ -25,7 +127,8 @@ impl Terminal {
             size: Size {
                 width: size.0,
                 height: size.1.saturating_sub(2),
-            }
+            },
+            color_support: ColorSupport::detect(),
         })
     }
 
@@ -37,21 +140,31 @@ impl Terminal {
         execute!(stdout(), terminal::Clear(ClearType::CurrentLine)).ok();
     }
 
+    pub fn clear_until_newline() {
+        execute!(stdout(), terminal::Clear(ClearType::UntilNewLine)).ok();
+    }
+
     pub fn quit() {
         Terminal::clear_screen();
         crossterm::terminal::disable_raw_mode().ok();
         println!("bye \r");
     }
 
+    #[must_use]
     pub fn size(&self) -> &Size{
         &self.size
     }
+
+    #[must_use]
+    pub fn color_support(&self) -> ColorSupport {
+        self.color_support
+    }
     pub fn cursor_position(position: &Position) {
         let Position { mut x, mut y } = position;
         x = x.saturating_add(1);
         y = y.saturating_add(1);
-        let x = x as u16;
-        let y = y as u16;
+        let x = u16::try_from(x).unwrap_or(u16::MAX);
+        let y = u16::try_from(y).unwrap_or(u16::MAX);
         queue!(stdout(),cursor::MoveTo(x - 1, y - 1)).ok();
     }
 
@@ -67,12 +180,49 @@ impl Terminal {
         stdout().flush()
     }
 
-    pub fn set_colors(colors: Colors) {
-        execute!(stdout(), SetColors(colors)).ok();
+    pub fn set_style(style: &Style) {
+        print!("{}", style.escape());
     }
     
-    pub fn reset_colors() {
-        execute!(stdout(), ResetColor).ok();
+    pub fn reset_style() {
+        execute!(stdout(), SetAttribute(Attribute::Reset)).ok();
+    }
+
+    pub fn set_clipboard(text: &str) -> Result<(), std::io::Error> {
+        let mut out = stdout();
+        write!(out, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+        out.flush()
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::Rgb { r, g, b }
+    }
+
+    #[test]
+    fn ansi256_uses_the_color_cube() {
+        assert!(ColorSupport::Ansi256.adapt(rgb(0, 0, 0)) == Color::AnsiValue(16));
+        assert!(ColorSupport::Ansi256.adapt(rgb(255, 0, 0)) == Color::AnsiValue(196));
+        assert!(ColorSupport::Ansi256.adapt(rgb(95, 135, 175)) == Color::AnsiValue(67));
+        assert!(ColorSupport::Ansi256.adapt(rgb(255, 255, 255)) == Color::AnsiValue(231));
+    }
+
+    #[test]
+    fn ansi256_uses_the_grayscale_ramp_for_grays() {
+        assert!(ColorSupport::Ansi256.adapt(rgb(128, 128, 128)) == Color::AnsiValue(244));
+        assert!(ColorSupport::Ansi256.adapt(rgb(30, 30, 30)) == Color::AnsiValue(234));
+    }
+
+    #[test]
+    fn ansi16_picks_the_nearest_named_color() {
+        assert!(ColorSupport::Ansi16.adapt(rgb(250, 10, 10)) == Color::Red);
+        assert!(ColorSupport::Ansi16.adapt(rgb(0, 0, 200)) == Color::DarkBlue);
+        assert!(ColorSupport::Ansi16.adapt(rgb(120, 120, 120)) == Color::DarkGrey);
+        assert!(ColorSupport::Ansi16.adapt(Color::Blue) == Color::Blue);
+        assert!(ColorSupport::TrueColor.adapt(rgb(1, 2, 3)) == rgb(1, 2, 3));
+    }
+}
@@ -0,0 +1,374 @@
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::highlighting;
+use crate::ColorSupport;
+use crate::syntax::config_dir;
+
+const STYLE_NAMES: &[&str] = &[
+    "text", "number", "match", "string", "character", "comment", "keyword", "type", "lifetime", "macro",
+    "selection", "status_bar", "status_bar_inactive", "message_bar", "line_number", "tilde", "separator",
+];
+
+#[derive(Default, PartialEq, Clone, Copy)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    fn foreground(r: u8, g: u8, b: u8) -> Self {
+        Self { foreground: Some(Color::Rgb { r, g, b }), ..Self::default() }
+    }
+
+    fn background(r: u8, g: u8, b: u8) -> Self {
+        Self { background: Some(Color::Rgb { r, g, b }), ..Self::default() }
+    }
+
+    fn colors(foreground: (u8, u8, u8), background: (u8, u8, u8)) -> Self {
+        Self {
+            background: Some(Color::Rgb { r: background.0, g: background.1, b: background.2 }),
+            ..Self::foreground(foreground.0, foreground.1, foreground.2)
+        }
+    }
+
+    fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    fn italic(self) -> Self {
+        Self { italic: true, ..self }
+    }
+
+    #[must_use]
+    pub fn overlay(self, other: Self) -> Self {
+        Self {
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            bold: self.bold || other.bold,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+        }
+    }
+
+    #[must_use]
+    pub fn escape(&self) -> String {
+        let mut escape = format!("{}", SetAttribute(Attribute::Reset));
+        if let Some(color) = self.foreground {
+            escape.push_str(&ansi_escape(color, 0).unwrap_or_else(|| format!("{}", SetForegroundColor(color))));
+        }
+        if let Some(color) = self.background {
+            escape.push_str(&ansi_escape(color, 10).unwrap_or_else(|| format!("{}", SetBackgroundColor(color))));
+        }
+        if self.bold {
+            write!(escape, "{}", SetAttribute(Attribute::Bold)).ok();
+        }
+        if self.italic {
+            write!(escape, "{}", SetAttribute(Attribute::Italic)).ok();
+        }
+        if self.underline {
+            write!(escape, "{}", SetAttribute(Attribute::Underlined)).ok();
+        }
+        escape
+    }
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    name: String,
+    styles: HashMap<String, Style>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: Option<String>,
+    base: Option<String>,
+    #[serde(default)]
+    styles: HashMap<String, StyleFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleFile {
+    foreground: Option<String>,
+    background: Option<String>,
+    #[serde(default)]
+    bold: bool,
+    #[serde(default)]
+    italic: bool,
+    #[serde(default)]
+    underline: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn style(&self, name: &str) -> Style {
+        self.styles.get(name).copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn on_text(&self, name: &str) -> Style {
+        self.style("text").overlay(self.style(name))
+    }
+
+    #[must_use]
+    pub fn highlight(&self, highlighting_type: highlighting::Type) -> Style {
+        self.on_text(highlighting_type.name())
+    }
+
+    #[must_use]
+    pub fn adapt(&self, colors: ColorSupport) -> Self {
+        let styles = self.styles.iter().map(|(name, style)| {
+            let style = Style {
+                foreground: style.foreground.map(|color| colors.adapt(color)),
+                background: style.background.map(|color| colors.adapt(color)),
+                ..*style
+            };
+            (name.clone(), style)
+        });
+        Self { name: self.name.clone(), styles: styles.collect() }
+    }
+
+    #[must_use]
+    pub fn built_in() -> Vec<Self> {
+        vec![Self::dark(), Self::light(), Self::solarized_dark(), Self::solarized_light()]
+    }
+
+    #[must_use]
+    pub fn load_all(themes: &[Self]) -> (Vec<Self>, Vec<String>) {
+        let mut loaded: Vec<Self> = Vec::new();
+        let mut errors = Vec::new();
+        if let Some(dir) = config_dir().map(|dir| dir.join("themes")) {
+            let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+                .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+                .unwrap_or_default();
+            paths.retain(|path| path.extension().is_some_and(|extension| extension == "toml"));
+            paths.sort();
+            for path in paths {
+                let known: Vec<Self> = themes.iter().chain(loaded.iter()).cloned().collect();
+                match Self::load(&path, &known) {
+                    Ok(theme) => loaded.push(theme),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        (loaded, errors)
+    }
+
+    pub fn load(path: &Path, themes: &[Self]) -> Result<Self, String> {
+        let source = path.display();
+        let text = fs::read_to_string(path).map_err(|error| format!("{source}: {error}"))?;
+        let file: ThemeFile = toml::from_str(&text).map_err(|error| {
+            let line = error.span().map_or(1, |span| text[..span.start].matches('\n').count().saturating_add(1));
+            format!("{}:{}: {}", source, line, error.message())
+        })?;
+        let mut theme = match &file.base {
+            Some(base) => themes
+                .iter()
+                .find(|theme| theme.name == *base)
+                .cloned()
+                .ok_or_else(|| format!("{source}: unknown base theme `{base}`"))?,
+            None => Self::dark(),
+        };
+        theme.name = file.name.unwrap_or_else(|| {
+            path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        });
+        for (name, style) in file.styles {
+            if !STYLE_NAMES.contains(&name.as_str()) {
+                return Err(format!("{}: unknown style `{}` (expected one of {})", source, name, STYLE_NAMES.join(", ")));
+            }
+            let color = |value: &Option<String>, field: &str| {
+                value.as_deref().map(parse_color).transpose().map_err(|value| {
+                    format!("{source}: invalid color `{value}` for {name}.{field}")
+                })
+            };
+            let style = Style {
+                foreground: color(&style.foreground, "foreground")?,
+                background: color(&style.background, "background")?,
+                bold: style.bold,
+                italic: style.italic,
+                underline: style.underline,
+            };
+            theme.styles.insert(name, style);
+        }
+        Ok(theme)
+    }
+
+    fn from_styles(name: &str, styles: Vec<(&str, Style)>) -> Self {
+        Self {
+            name: name.to_string(),
+            styles: styles.into_iter().map(|(name, style)| (name.to_string(), style)).collect(),
+        }
+    }
+
+    fn dark() -> Self {
+        Self::from_styles("dark", vec![
+            ("text", Style::colors((255, 255, 255), (30, 30, 30))),
+            ("number", Style::foreground(220, 163, 163)),
+            ("match", Style::foreground(38, 139, 210)),
+            ("string", Style::foreground(211, 54, 130)),
+            ("character", Style::foreground(108, 113, 196)),
+            ("comment", Style::foreground(133, 153, 0)),
+            ("keyword", Style::foreground(181, 137, 0)),
+            ("type", Style::foreground(42, 161, 152)),
+            ("lifetime", Style::foreground(203, 75, 22)),
+            ("macro", Style::foreground(220, 50, 47)),
+            ("selection", Style::background(88, 110, 117)),
+            ("status_bar", Style::colors((63, 63, 63), (239, 239, 239))),
+            ("status_bar_inactive", Style::colors((220, 220, 220), (120, 120, 120))),
+            ("message_bar", Style::foreground(255, 255, 255)),
+            ("line_number", Style::foreground(101, 123, 131)),
+            ("tilde", Style::foreground(88, 110, 117)),
+            ("separator", Style::foreground(120, 120, 120)),
+        ])
+    }
+
+    fn light() -> Self {
+        Self::from_styles("light", vec![
+            ("text", Style::colors((40, 40, 40), (250, 250, 250))),
+            ("number", Style::foreground(152, 104, 1)),
+            ("match", Style::colors((255, 255, 255), (38, 139, 210))),
+            ("string", Style::foreground(163, 21, 21)),
+            ("character", Style::foreground(128, 0, 128)),
+            ("comment", Style::foreground(0, 128, 0).italic()),
+            ("keyword", Style::foreground(0, 0, 255).bold()),
+            ("type", Style::foreground(38, 127, 153)),
+            ("lifetime", Style::foreground(175, 0, 219)),
+            ("macro", Style::foreground(121, 94, 38)),
+            ("selection", Style::background(173, 214, 255)),
+            ("status_bar", Style::colors((255, 255, 255), (60, 60, 60))),
+            ("status_bar_inactive", Style::colors((60, 60, 60), (200, 200, 200))),
+            ("message_bar", Style::foreground(40, 40, 40)),
+            ("line_number", Style::foreground(150, 150, 150)),
+            ("tilde", Style::foreground(180, 180, 180)),
+            ("separator", Style::foreground(180, 180, 180)),
+        ])
+    }
+
+    fn solarized_dark() -> Self {
+        Self::from_styles("solarized-dark", vec![
+            ("text", Style::colors((131, 148, 150), (0, 43, 54))),
+            ("number", Style::foreground(108, 113, 196)),
+            ("match", Style::colors((0, 43, 54), (181, 137, 0))),
+            ("string", Style::foreground(42, 161, 152)),
+            ("character", Style::foreground(42, 161, 152)),
+            ("comment", Style::foreground(88, 110, 117).italic()),
+            ("keyword", Style::foreground(133, 153, 0)),
+            ("type", Style::foreground(181, 137, 0)),
+            ("lifetime", Style::foreground(203, 75, 22)),
+            ("macro", Style::foreground(38, 139, 210)),
+            ("selection", Style::background(7, 54, 66)),
+            ("status_bar", Style::colors((147, 161, 161), (7, 54, 66)).bold()),
+            ("status_bar_inactive", Style::colors((88, 110, 117), (7, 54, 66))),
+            ("message_bar", Style::foreground(147, 161, 161)),
+            ("line_number", Style::foreground(88, 110, 117)),
+            ("tilde", Style::foreground(88, 110, 117)),
+            ("separator", Style::foreground(7, 54, 66)),
+        ])
+    }
+
+    fn solarized_light() -> Self {
+        Self::from_styles("solarized-light", vec![
+            ("text", Style::colors((101, 123, 131), (253, 246, 227))),
+            ("number", Style::foreground(108, 113, 196)),
+            ("match", Style::colors((253, 246, 227), (181, 137, 0))),
+            ("string", Style::foreground(42, 161, 152)),
+            ("character", Style::foreground(42, 161, 152)),
+            ("comment", Style::foreground(147, 161, 161).italic()),
+            ("keyword", Style::foreground(133, 153, 0)),
+            ("type", Style::foreground(181, 137, 0)),
+            ("lifetime", Style::foreground(203, 75, 22)),
+            ("macro", Style::foreground(38, 139, 210)),
+            ("selection", Style::background(238, 232, 213)),
+            ("status_bar", Style::colors((88, 110, 117), (238, 232, 213)).bold()),
+            ("status_bar_inactive", Style::colors((147, 161, 161), (238, 232, 213))),
+            ("message_bar", Style::foreground(88, 110, 117)),
+            ("line_number", Style::foreground(147, 161, 161)),
+            ("tilde", Style::foreground(147, 161, 161)),
+            ("separator", Style::foreground(238, 232, 213)),
+        ])
+    }
+}
+
+fn ansi_escape(color: Color, offset: u8) -> Option<String> {
+    let code: u8 = match color {
+        Color::Black => 30,
+        Color::DarkRed => 31,
+        Color::DarkGreen => 32,
+        Color::DarkYellow => 33,
+        Color::DarkBlue => 34,
+        Color::DarkMagenta => 35,
+        Color::DarkCyan => 36,
+        Color::Grey => 37,
+        Color::Reset => 39,
+        Color::DarkGrey => 90,
+        Color::Red => 91,
+        Color::Green => 92,
+        Color::Yellow => 93,
+        Color::Blue => 94,
+        Color::Magenta => 95,
+        Color::Cyan => 96,
+        Color::White => 97,
+        _ => return None,
+    };
+    Some(format!("\x1b[{}m", code + offset))
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |index: usize| hex.get(index..index + 2).and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        if hex.len() == 6 {
+            if let (Some(r), Some(g), Some(b)) = (channel(0), channel(2), channel(4)) {
+                return Ok(Color::Rgb { r, g, b });
+            }
+        }
+        return Err(value.to_string());
+    }
+    if value.eq_ignore_ascii_case("reset") || value.eq_ignore_ascii_case("default") {
+        return Ok(Color::Reset);
+    }
+    Color::try_from(value).map_err(|()| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_themes_define_every_style() {
+        for theme in Theme::built_in() {
+            for name in STYLE_NAMES {
+                assert!(theme.styles.contains_key(*name), "{} has no `{}` style", theme.name, name);
+            }
+            assert!(theme.style("text").background.is_some(), "{} has no text background", theme.name);
+        }
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert!(parse_color("#ff8000") == Ok(Color::Rgb { r: 255, g: 128, b: 0 }));
+        assert!(parse_color("default") == Ok(Color::Reset));
+        assert!(parse_color("dark_blue") == Ok(Color::DarkBlue));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("chartreuse").is_err());
+    }
+}
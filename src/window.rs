@@ -0,0 +1,247 @@
+use crate::Position;
+
+const MIN_RATIO: u16 = 10;
+const MAX_RATIO: u16 = 90;
+const MIN_HEIGHT: usize = 2;
+const MIN_WIDTH: usize = 1;
+
+#[derive(Default, Clone)]
+pub struct Window {
+    pub buffer: usize,
+    pub cursor_position: Position,
+    pub offset: Offset,
+}
+
+#[derive(Default, Clone)]
+pub struct Offset {
+    pub x: usize,
+    pub y: usize,
+    pub segment: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    #[must_use]
+    pub fn can_split(&self, split: Split) -> bool {
+        match split {
+            Split::Horizontal => self.height >= MIN_HEIGHT * 2,
+            Split::Vertical => self.width > MIN_WIDTH * 2,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Split {
+    Horizontal,
+    Vertical,
+}
+
+pub enum Layout {
+    Window(usize),
+    Split {
+        split: Split,
+        ratio: u16,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    pub fn split(&mut self, target: usize, window: usize, split: Split) -> bool {
+        match self {
+            Layout::Window(index) if *index == target => {
+                *self = Layout::Split {
+                    split,
+                    ratio: 50,
+                    first: Box::new(Layout::Window(target)),
+                    second: Box::new(Layout::Window(window)),
+                };
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split { first, second, .. } => {
+                first.split(target, window, split) || second.split(target, window, split)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, target: usize) -> bool {
+        let removed = self.detach(target);
+        if removed {
+            self.renumber(target);
+        }
+        removed
+    }
+
+    fn detach(&mut self, target: usize) -> bool {
+        if let Layout::Split { first, second, .. } = self {
+            let remaining = match (first.as_mut(), second.as_mut()) {
+                (Layout::Window(index), other) | (other, Layout::Window(index)) if *index == target => {
+                    std::mem::replace(other, Layout::Window(target))
+                }
+                _ => return first.detach(target) || second.detach(target),
+            };
+            *self = remaining;
+            return true;
+        }
+        false
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Layout::Window(index) => {
+                if *index > removed {
+                    *index -= 1;
+                }
+            }
+            Layout::Split { first, second, .. } => {
+                first.renumber(removed);
+                second.renumber(removed);
+            }
+        }
+    }
+
+    pub fn resize(&mut self, target: usize, delta: i16) -> bool {
+        if let Layout::Split { ratio, first, second, .. } = self {
+            if first.resize(target, delta) || second.resize(target, delta) {
+                return true;
+            }
+            let change = if first.contains(target) {
+                delta
+            } else if second.contains(target) {
+                delta.saturating_neg()
+            } else {
+                return false;
+            };
+            let resized = ratio.saturating_add_signed(change).clamp(MIN_RATIO, MAX_RATIO);
+            *ratio = resized;
+            return true;
+        }
+        false
+    }
+
+    fn contains(&self, target: usize) -> bool {
+        match self {
+            Layout::Window(index) => *index == target,
+            Layout::Split { first, second, .. } => first.contains(target) || second.contains(target),
+        }
+    }
+
+    #[must_use]
+    pub fn rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        match self {
+            Layout::Window(index) => vec![(*index, area)],
+            Layout::Split { split: Split::Horizontal, ratio, first, second } => {
+                let top = divide(area.height, *ratio, MIN_HEIGHT);
+                let mut rects = first.rects(Rect { height: top, ..area });
+                rects.append(&mut second.rects(Rect {
+                    y: area.y + top,
+                    height: area.height - top,
+                    ..area
+                }));
+                rects
+            }
+            Layout::Split { split: Split::Vertical, ratio, first, second } => {
+                let available = area.width.saturating_sub(1);
+                let left = divide(available, *ratio, MIN_WIDTH);
+                let mut rects = first.rects(Rect { width: left, ..area });
+                rects.append(&mut second.rects(Rect {
+                    x: area.x + left + 1,
+                    width: available.saturating_sub(left),
+                    ..area
+                }));
+                rects
+            }
+        }
+    }
+}
+
+fn divide(length: usize, ratio: u16, minimum: usize) -> usize {
+    let upper = length.saturating_sub(minimum);
+    (length * usize::from(ratio) / 100).clamp(minimum.min(upper), upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect { x: 0, y: 0, width: 80, height: 24 };
+
+    fn split_all(layout: &mut Layout, windows: usize, split: Split) {
+        for window in 1..windows {
+            layout.split(window - 1, window, split);
+        }
+    }
+
+    #[test]
+    fn horizontal_split_stacks_windows() {
+        let mut layout = Layout::Window(0);
+        layout.split(0, 1, Split::Horizontal);
+        let rects = layout.rects(SCREEN);
+        assert_eq!(rects.len(), 2);
+        assert_eq!((rects[0].1.y, rects[0].1.height), (0, 12));
+        assert_eq!((rects[1].1.y, rects[1].1.height), (12, 12));
+        assert_eq!(rects[1].1.width, 80);
+    }
+
+    #[test]
+    fn vertical_split_leaves_room_for_the_separator() {
+        let mut layout = Layout::Window(0);
+        layout.split(0, 1, Split::Vertical);
+        let rects = layout.rects(SCREEN);
+        assert_eq!((rects[0].1.x, rects[0].1.width), (0, 39));
+        assert_eq!((rects[1].1.x, rects[1].1.width), (40, 40));
+    }
+
+    #[test]
+    fn tiny_areas_do_not_panic() {
+        let mut layout = Layout::Window(0);
+        split_all(&mut layout, 8, Split::Horizontal);
+        for height in 0..4 {
+            let rects = layout.rects(Rect { height, ..SCREEN });
+            assert_eq!(rects.len(), 8);
+            assert_eq!(rects.iter().map(|(_, rect)| rect.height).sum::<usize>(), height);
+        }
+        let mut layout = Layout::Window(0);
+        split_all(&mut layout, 8, Split::Vertical);
+        for width in 0..4 {
+            assert_eq!(layout.rects(Rect { width, ..SCREEN }).len(), 8);
+        }
+    }
+
+    #[test]
+    fn nested_splits_keep_the_minimum_height() {
+        let mut layout = Layout::Window(0);
+        let mut windows = 1;
+        loop {
+            let rects = layout.rects(SCREEN);
+            let last = rects.iter().find(|(index, _)| *index == windows - 1).unwrap().1;
+            if !last.can_split(Split::Horizontal) {
+                break;
+            }
+            layout.split(windows - 1, windows, Split::Horizontal);
+            windows += 1;
+        }
+        assert!(windows > 3);
+        assert!(layout.rects(SCREEN).iter().all(|(_, rect)| rect.height >= MIN_HEIGHT));
+    }
+
+    #[test]
+    fn resize_and_remove() {
+        let mut layout = Layout::Window(0);
+        layout.split(0, 1, Split::Horizontal);
+        assert!(layout.resize(0, 100));
+        assert_eq!(layout.rects(SCREEN)[0].1.height, 21);
+        assert!(layout.remove(0));
+        let rects = layout.rects(SCREEN);
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].0, 0);
+    }
+}